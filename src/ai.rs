@@ -0,0 +1,113 @@
+//! Простой компьютерный противник.
+//! Поворачивает к ближайшему фрукту и старается не врезаться в стены.
+
+use macroquad::prelude::*;
+
+use crate::config::GameConfig;
+use crate::fruit::Fruit;
+use crate::snake::Snake;
+
+/// Во сколько раз скорость поворота больше угла до цели.
+/// Чем больше, тем резче змейка доворачивает на фрукт.
+const STEERING_GAIN: f32 = 4.0;
+
+/// На каком расстоянии от стены змейка начинает от неё отворачивать.
+const WALL_MARGIN: f32 = 0.2;
+
+impl Snake {
+    /// Ближайший к голове фрукт.
+    pub fn nearest_fruit<'a>(&self, fruits: &'a [Fruit]) -> Option<&'a Fruit> {
+        let head = self.head.position();
+        fruits.iter().min_by(|a, b| {
+            head.distance(a.position)
+                .total_cmp(&head.distance(b.position))
+        })
+    }
+
+    /// Скорость поворота, с которой компьютер ведёт змейку к фрукту.
+    /// Положительное значение - поворот налево, отрицательное - направо.
+    pub fn ai_rotation(&self, fruit: &Fruit, config: &GameConfig) -> f32 {
+        let head = self.head.position();
+        let max_rotation = config.tuning.rotation_per_sec;
+
+        // У стены важнее не разбиться, поэтому отворачиваем к центру поля изо всех сил.
+        if !config.walls_wrap() && self.heads_into_wall(config) {
+            let to_center = -head;
+            return signed_angle(self.head.direction, to_center).signum() * max_rotation;
+        }
+
+        self.rotation_towards(fruit.position, config)
+    }
+
+    /// Скорость поворота к точке поля, пропорциональная углу до неё.
+    /// Так же змейка следует за курсором мыши.
+    pub fn rotation_towards(&self, target: Vec2, config: &GameConfig) -> f32 {
+        let max_rotation = config.tuning.rotation_per_sec;
+        let angle = signed_angle(self.head.direction, target - self.head.position());
+        (angle * STEERING_GAIN).clamp(-max_rotation, max_rotation)
+    }
+
+    /// Голова у стены и движется в её сторону.
+    fn heads_into_wall(&self, config: &GameConfig) -> bool {
+        let head = self.head.position();
+        let direction = self.head.direction;
+        let limit = config.half_extents() - WALL_MARGIN;
+        (head.x.abs() > limit.x && head.x * direction.x > 0.0)
+            || (head.y.abs() > limit.y && head.y * direction.y > 0.0)
+    }
+}
+
+/// Угол поворота от одного направления к другому, от -PI до PI.
+/// Положительный угол означает поворот против часовой стрелки.
+fn signed_angle(from: Vec2, to: Vec2) -> f32 {
+    from.perp_dot(to).atan2(from.dot(to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fruit::FRUIT_RADIUS;
+    use crate::snake::ROTATION_PER_SEC;
+
+    #[test]
+    fn goes_straight_to_fruit_ahead() {
+        let snake = Snake::default();
+        let rotation = snake.ai_rotation(
+            &Fruit::at(Vec2::new(0.5, 0.0), FRUIT_RADIUS),
+            &GameConfig::default(),
+        );
+        assert_eq!(rotation, 0.0);
+    }
+
+    #[test]
+    fn turns_towards_fruit() {
+        let snake = Snake::default();
+        let config = GameConfig::default();
+        let left = snake.ai_rotation(&Fruit::at(Vec2::new(0.0, 0.5), FRUIT_RADIUS), &config);
+        let right = snake.ai_rotation(&Fruit::at(Vec2::new(0.0, -0.5), FRUIT_RADIUS), &config);
+        assert_eq!(left, ROTATION_PER_SEC);
+        assert_eq!(right, -ROTATION_PER_SEC);
+    }
+
+    #[test]
+    fn turns_away_from_wall() {
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(0.9, 0.1);
+
+        // Фрукт прямо по курсу, но за ним стена, поэтому отворачиваем к центру.
+        let fruit = Fruit::at(Vec2::new(0.95, 0.1), FRUIT_RADIUS);
+        let rotation = snake.ai_rotation(&fruit, &GameConfig::default());
+        assert_eq!(rotation.abs(), ROTATION_PER_SEC);
+    }
+
+    #[test]
+    fn picks_nearest_fruit() {
+        let snake = Snake::default();
+        let fruits = [
+            Fruit::at(Vec2::new(0.8, 0.0), FRUIT_RADIUS),
+            Fruit::at(Vec2::new(0.0, 0.2), FRUIT_RADIUS),
+        ];
+        let nearest = snake.nearest_fruit(&fruits).unwrap();
+        assert_eq!(nearest.position, fruits[1].position);
+    }
+}
@@ -0,0 +1,85 @@
+//! Звуки и фоновая музыка.
+
+use macroquad::audio::{
+    load_sound, play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound,
+};
+use macroquad::prelude::*;
+
+use crate::state::GameState;
+
+/// Загружаем звук.
+/// Если звук отключён при сборке или файл не удалось загрузить, играем без него.
+pub async fn load_optional_sound(path: &str) -> Option<Sound> {
+    if !cfg!(feature = "audio") {
+        return None;
+    }
+    match load_sound(path).await {
+        Ok(sound) => Some(sound),
+        Err(e) => {
+            eprintln!("Failed to load sound {path}: {e}");
+            None
+        }
+    }
+}
+
+/// Громкость фоновой музыки.
+pub const MUSIC_VOLUME: f32 = 0.4;
+
+/// Фоновая музыка, которая играет по кругу во время игры.
+pub struct Music {
+    pub sound: Option<Sound>,
+    /// Запущена ли музыка.
+    pub playing: bool,
+    /// Выключил ли игрок музыку. Выбор сохраняется до конца сеанса.
+    pub muted: bool,
+    /// Текущая громкость, чтобы не выставлять её заново каждый кадр.
+    pub volume: f32,
+}
+
+impl Music {
+    /// Музыка пока не играет.
+    pub fn new(sound: Option<Sound>) -> Self {
+        Self {
+            sound,
+            playing: false,
+            muted: false,
+            volume: MUSIC_VOLUME,
+        }
+    }
+
+    /// Включаем или выключаем музыку.
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+    }
+
+    /// Запускаем или останавливаем музыку в зависимости от состояния игры.
+    /// В меню и после поражения музыка остановлена. На паузе она лишь затихает,
+    /// потому что macroquad не умеет продолжать звук с того же места.
+    pub fn update(&mut self, state: GameState) {
+        let Some(sound) = &self.sound else {
+            return;
+        };
+
+        let running = matches!(state, GameState::Playing | GameState::Paused { .. });
+        if running && !self.playing {
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    looped: true,
+                    volume: self.volume,
+                },
+            );
+            self.playing = true;
+        } else if !running && self.playing {
+            stop_sound(sound);
+            self.playing = false;
+        }
+
+        let audible = !self.muted && state == GameState::Playing;
+        let volume = if audible { MUSIC_VOLUME } else { 0.0 };
+        if volume != self.volume {
+            set_sound_volume(sound, volume);
+            self.volume = volume;
+        }
+    }
+}
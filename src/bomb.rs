@@ -0,0 +1,145 @@
+//! Бомбы, которых змейке нужно избегать.
+
+use macroquad::prelude::*;
+
+use crate::config::GameConfig;
+use crate::fruit::Fruit;
+use crate::player::Player;
+use crate::random::random_position;
+use crate::render::ScreenTransform;
+
+/// Сколько бомб лежит на поле.
+pub const BOMBS_COUNT: usize = 2;
+
+/// Радиус бомбы.
+pub const BOMB_RADIUS: f32 = 0.05;
+
+/// Через сколько секунд бомба перебирается на новое место.
+pub const BOMB_RELOCATE_INTERVAL: f32 = 7.0;
+
+/// Ближе этого расстояния к сегментам змеек и к фруктам бомбу не кладём.
+pub const BOMB_CLEARANCE: f32 = 0.15;
+
+/// Ближе этого расстояния к голове змейки бомбу не кладём, чтобы на неё не наехать сразу.
+pub const BOMB_HEAD_CLEARANCE: f32 = 0.4;
+
+/// Сколько случайных мест пробуем, прежде чем оставить бомбу на старом месте.
+const BOMB_SPAWN_ATTEMPTS: usize = 50;
+
+/// Цвет фитиля бомбы.
+const BOMB_FUSE_COLOR: Color = ORANGE;
+
+/// Бомба. Касание её головой завершает игру, как удар о стену.
+pub struct Bomb {
+    pub position: Vec2,
+    /// Сколько секунд бомба ещё лежит на этом месте.
+    pub time_left: f32,
+}
+
+impl Bomb {
+    /// Бомба появляется в случайном месте поля, подальше от змеек и фруктов.
+    /// Если свободного места не нашлось, то её нет.
+    pub fn spawn(config: &GameConfig, players: &[Player], fruits: &[Fruit]) -> Option<Self> {
+        let mut bomb = Self {
+            position: Vec2::ZERO,
+            time_left: 0.0,
+        };
+        bomb.relocate(config, players, fruits).then_some(bomb)
+    }
+
+    /// Переносим бомбу на свободное место. Возвращаем, нашлось ли оно.
+    /// Если не нашлось, то бомба остаётся на старом месте до следующего переноса.
+    pub fn relocate(&mut self, config: &GameConfig, players: &[Player], fruits: &[Fruit]) -> bool {
+        self.time_left = BOMB_RELOCATE_INTERVAL;
+        let is_free = |position: Vec2| {
+            let near_body = players
+                .iter()
+                .flat_map(|player| player.snake.segments())
+                .chain(fruits.iter().map(|fruit| fruit.position))
+                .any(|other| other.distance(position) < BOMB_CLEARANCE);
+            let near_head = players.iter().any(|player| {
+                player.snake.head.position().distance(position) < BOMB_HEAD_CLEARANCE
+            });
+            !near_body && !near_head
+        };
+        let found = (0..BOMB_SPAWN_ATTEMPTS)
+            .map(|_| random_position(config) * 0.9)
+            .find(|&position| is_free(position));
+        if let Some(position) = found {
+            self.position = position;
+        }
+        found.is_some()
+    }
+
+    /// Отсчитываем время до переноса. Возвращаем, не пора ли перенести бомбу.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.time_left -= dt;
+        self.time_left <= 0.0
+    }
+
+    /// Проверка пересечения бомбы с заданным кругом.
+    pub fn intersect(&self, position: Vec2, radius: f32) -> bool {
+        self.position.distance(position) < radius + BOMB_RADIUS
+    }
+
+    /// Бомба - тёмный круг с горящим фитилём сверху справа.
+    pub fn draw(&self, screen: &ScreenTransform) {
+        let ppm = screen.pixels_per_meter;
+        let center = screen.to_screen(self.position);
+        let radius = BOMB_RADIUS * ppm;
+        let fuse_end = center + Vec2::new(1.0, -1.0) * radius * 1.2;
+        draw_line(center.x, center.y, fuse_end.x, fuse_end.y, 3.0, DARKBROWN);
+        draw_circle(center.x, center.y, radius, BLACK);
+        draw_circle(fuse_end.x, fuse_end.y, radius * 0.3, BOMB_FUSE_COLOR);
+    }
+}
+
+/// Раскладываем по полю новый набор бомб.
+pub fn spawn_bombs(config: &GameConfig, players: &[Player], fruits: &[Fruit]) -> Vec<Bomb> {
+    (0..BOMBS_COUNT)
+        .filter_map(|_| Bomb::spawn(config, players, fruits))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::player::{spawn_players, PlayersMode};
+    use crate::random::RNG_LOCK;
+
+    #[test]
+    fn bombs_never_spawn_on_snake() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        rand::srand(3);
+        let config = GameConfig::default();
+        let mut players = spawn_players(PlayersMode::TwoPlayers, &config);
+        for player in &mut players {
+            for _ in 0..30 {
+                player.snake.add_unit();
+                player.snake.go(0.05, 1.0, &config);
+            }
+        }
+        for _ in 0..100 {
+            for bomb in spawn_bombs(&config, &players, &[]) {
+                for player in &players {
+                    assert!(player
+                        .snake
+                        .segments()
+                        .all(|unit| unit.distance(bomb.position) >= BOMB_CLEARANCE));
+                    assert!(!player.snake.hits_bomb(std::slice::from_ref(&bomb)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bomb_relocates_after_interval() {
+        let mut bomb = Bomb {
+            position: Vec2::ZERO,
+            time_left: BOMB_RELOCATE_INTERVAL,
+        };
+        assert!(!bomb.update(BOMB_RELOCATE_INTERVAL / 2.0));
+        assert!(bomb.update(BOMB_RELOCATE_INTERVAL / 2.0));
+    }
+}
@@ -0,0 +1,163 @@
+//! Классический режим: змейка ходит по клеткам и поворачивает только под прямым углом.
+//! Клетка равна расстоянию между сегментами, поэтому тело переходит из клетки в клетку
+//! следом за головой. Чем быстрее змейка, тем чаще она делает ход.
+
+use macroquad::prelude::*;
+
+use crate::config::GameConfig;
+use crate::snake::{wrap_position, Snake, Unit, TRAIL_LENGTH};
+
+impl Snake {
+    /// Размер клетки классического режима.
+    pub fn cell_size(&self) -> f32 {
+        2.0 * self.radius
+    }
+
+    /// Перемещение змейки по клеткам. Нажатый поворот запоминается и выполняется
+    /// на ближайшем ходе. Зажатая клавиша поворачивает змейку только один раз.
+    pub fn go_classic(&mut self, dt: f32, rotation: f32, config: &GameConfig) {
+        if self.trail.len() == TRAIL_LENGTH {
+            self.trail.pop_front();
+        }
+        self.trail.push_back(self.head.position());
+
+        let turn = if rotation == 0.0 {
+            0.0
+        } else {
+            rotation.signum()
+        };
+        if turn != 0.0 && turn != self.turn_held {
+            self.queued_turn = turn;
+        }
+        self.turn_held = turn;
+
+        let cell = self.cell_size();
+        self.cell_progress += self.head.speed * self.head.speed_multiplier * dt;
+        while self.cell_progress >= cell {
+            self.cell_progress -= cell;
+            self.step_cell(config);
+        }
+        let field_size = config.field_extents().max_element();
+        self.grid.rebuild(&self.units, field_size);
+    }
+
+    /// Один ход: голова переходит в соседнюю клетку, а каждый сегмент - на место предыдущего.
+    /// Съеденный сегмент вырастает сразу на месте, которое освободил хвост.
+    fn step_cell(&mut self, config: &GameConfig) {
+        let cell = self.cell_size();
+        let direction = snap_direction(self.head.direction);
+        self.head.direction = if self.queued_turn == 0.0 {
+            direction
+        } else {
+            direction.perp() * self.queued_turn
+        };
+        self.queued_turn = 0.0;
+        self.head.angular_velocity = 0.0;
+
+        let mut prev = self.head.position();
+        for unit in &mut self.units {
+            std::mem::swap(&mut unit.position, &mut prev);
+        }
+        if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+            self.units.push(Unit { position: prev });
+        }
+
+        let head = (self.head.position() / cell).round() * cell + self.head.direction * cell;
+        self.head.unit.position = if config.walls_wrap() {
+            wrap_position(head, config.field_extents())
+        } else {
+            head
+        };
+    }
+
+    /// Поворот компьютера, мыши или стика для классического режима.
+    /// Плавный поворот в нём не нужен, поэтому отдаём отдельные нажатия: поворачиваем,
+    /// только если к цели нужно заметно довернуть, и между нажатиями отпускаем клавишу.
+    pub fn grid_turn(&self, rotation: f32, config: &GameConfig) -> f32 {
+        let max_rotation = config.tuning.rotation_per_sec;
+        if self.turn_held != 0.0 || rotation.abs() < max_rotation / 2.0 {
+            return 0.0;
+        }
+        rotation.signum() * max_rotation
+    }
+}
+
+/// Ближайшее к заданному направление вдоль одной из осей.
+fn snap_direction(direction: Vec2) -> Vec2 {
+    if direction.x.abs() >= direction.y.abs() {
+        Vec2::new(direction.x.signum(), 0.0)
+    } else {
+        Vec2::new(0.0, direction.y.signum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classic() -> GameConfig {
+        GameConfig {
+            classic: true,
+            ..GameConfig::default()
+        }
+    }
+
+    #[test]
+    fn head_moves_cell_by_cell_and_turns_once_per_press() {
+        let config = classic();
+        let mut snake = Snake::with_length(2, &config.tuning);
+        let cell = snake.cell_size();
+        let step = cell / snake.head.speed * 1.001;
+
+        snake.go_classic(step * 0.5, 0.0, &config);
+        assert_eq!(snake.head.position(), Vec2::ZERO);
+        snake.go_classic(step * 0.5, 0.0, &config);
+        assert!(snake.head.position().distance(Vec2::new(cell, 0.0)) < 1e-6);
+
+        // Зажатый поворот налево поворачивает только на один прямой угол.
+        for _ in 0..3 {
+            snake.go_classic(step, 1.0, &config);
+        }
+        assert_eq!(snake.head.direction, Vec2::Y);
+        assert!(snake.head.position().distance(Vec2::new(cell, 3.0 * cell)) < 1e-5);
+
+        // Тело переходит из клетки в клетку следом за головой.
+        assert!(
+            snake.units[0]
+                .position
+                .distance(Vec2::new(cell, 2.0 * cell))
+                < 1e-5
+        );
+        assert!(snake.units[1].position.distance(Vec2::new(cell, cell)) < 1e-5);
+    }
+
+    #[test]
+    fn classic_snake_grows_and_hits_walls() {
+        let config = classic();
+        let mut snake = Snake::default();
+        let step = snake.cell_size() / snake.head.speed * 1.001;
+        snake.add_unit();
+        snake.go_classic(step, 0.0, &config);
+        assert_eq!(snake.units.len(), 1);
+        assert_eq!(snake.units[0].position, Vec2::ZERO);
+        assert_eq!(snake.length(), 2);
+
+        let moves = (config.half_size() / snake.cell_size()) as usize + 1;
+        for _ in 0..moves {
+            snake.go_classic(step, 0.0, &config);
+        }
+        assert!(snake.death_cause(&config).is_some());
+    }
+
+    #[test]
+    fn continuous_steering_becomes_separate_presses() {
+        let config = classic();
+        let mut snake = Snake::default();
+        let max_rotation = config.tuning.rotation_per_sec;
+        assert_eq!(snake.grid_turn(0.1, &config), 0.0);
+        assert_eq!(snake.grid_turn(-max_rotation, &config), -max_rotation);
+        snake.go_classic(0.001, -max_rotation, &config);
+        assert_eq!(snake.grid_turn(-max_rotation, &config), 0.0);
+    }
+}
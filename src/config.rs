@@ -0,0 +1,603 @@
+//! Настройки игры и размеры поля.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+use std::fs;
+
+use crate::fruit::FRUIT_RADIUS;
+use crate::lang::{Strings, LANGUAGES};
+use crate::render::{Camera, Theme, COLORBLIND_THEME, THEMES};
+use crate::snake::{
+    Skin, HEAD_GRIP, INIT_SPEED, ROTATION_PER_SEC, SELF_COLLISION_GRACE, UNIT_RADIUS,
+};
+use crate::storage::data_file_path;
+
+// Задаём параметры. Размеры будем задавать в метрах, а углы в радианах.
+
+/// Размер игрового поля по умолчанию.
+pub const FIELD_SIZE: f32 = 2.0;
+
+/// Наименьший и наибольший размер поля, который можно выбрать между раундами.
+pub const FIELD_SIZE_RANGE: (f32, f32) = (1.0, 4.0);
+
+/// На сколько меняется размер поля при нажатии + или -.
+pub const FIELD_SIZE_STEP: f32 = 0.5;
+
+/// Между какими отношениями ширины поля к его высоте можно выбирать в меню.
+pub const FIELD_ASPECTS: [f32; 3] = [1.0, 1.5, 2.0];
+
+/// Какую часть поля видно на экране. Поле побольше уже не помещается целиком,
+/// и камера следует за головой змейки.
+pub const MAX_VIEW_SIZE: f32 = FIELD_SIZE;
+
+/// С какой скоростью стены сдвигаются к центру в режиме внезапной смерти.
+pub const SHRINK_PER_SEC: f32 = 0.01;
+
+/// Меньше этой половины размера поле в режиме внезапной смерти не сжимается.
+pub const MIN_HALF_SIZE: f32 = 0.3;
+
+/// Сколько секунд длится раунд в игре на время.
+pub const TIMED_ROUND_DURATION: f32 = 60.0;
+
+/// До какой длины нужно дорасти, чтобы победить в игре на длину.
+pub const TARGET_LENGTH: u32 = 30;
+
+/// Радиус видимого круга вокруг головы в режиме тумана по умолчанию.
+pub const VISION_RADIUS: f32 = 0.45;
+
+/// Стартовые длины змейки, которые можно выбрать в меню.
+pub const STARTING_LENGTHS: [usize; 4] = [0, 5, 10, 20];
+
+/// Файл с игровыми константами рядом с исполняемым файлом игры.
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Во сколько раз самый маленький фрукт меньше самого крупного.
+const SMALLEST_FRUIT_SHARE: f32 = 0.5;
+
+/// Игровые константы, которые можно подобрать в `config.toml`, не пересобирая игру.
+/// Параметры, которых нет в файле, берутся по умолчанию.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Начальная скорость змеи.
+    pub init_speed: f32,
+    /// Радиус элемента змеи.
+    pub unit_radius: f32,
+    /// Радиус самого крупного фрукта.
+    pub fruit_radius: f32,
+    /// Скорость вращения змейки (радианы в секунду).
+    pub rotation_per_sec: f32,
+    /// Размер игрового поля в начале игры.
+    pub field_size: f32,
+    /// Сколько первых сегментов за головой не считаются столкновением.
+    /// Чем больше, тем прощаются более крутые повороты.
+    pub self_collision_grace: usize,
+    /// Сцепление головы с полем. Чем оно меньше, тем сильнее голову заносит на поворотах.
+    pub head_grip: f32,
+    /// Сколько видно вокруг головы в режиме тумана.
+    pub vision_radius: f32,
+}
+
+impl Config {
+    /// Читаем константы из `config.toml`.
+    /// Если файла нет или его не удалось разобрать, то играем с константами по умолчанию.
+    pub fn load() -> Self {
+        let Some(path) = data_file_path(CONFIG_FILE_NAME) else {
+            return Self::default();
+        };
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        Self::from_toml(&text).unwrap_or_else(|e| {
+            eprintln!("Failed to parse config from {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    /// Разбираем константы из текста в формате TOML.
+    /// Значения, с которыми играть нельзя, заменяем значениями по умолчанию.
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text).map(Self::validated)
+    }
+
+    /// Проверяем каждую константу. Размеры и скорости должны быть положительными,
+    /// а поле - не меньше и не больше тех, что можно выбрать в меню.
+    fn validated(self) -> Self {
+        let defaults = Self::default();
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+        let (min_size, max_size) = FIELD_SIZE_RANGE;
+        Self {
+            init_speed: checked("init_speed", self.init_speed, positive, defaults.init_speed),
+            unit_radius: checked(
+                "unit_radius",
+                self.unit_radius,
+                positive,
+                defaults.unit_radius,
+            ),
+            fruit_radius: checked(
+                "fruit_radius",
+                self.fruit_radius,
+                positive,
+                defaults.fruit_radius,
+            ),
+            rotation_per_sec: checked(
+                "rotation_per_sec",
+                self.rotation_per_sec,
+                positive,
+                defaults.rotation_per_sec,
+            ),
+            field_size: checked(
+                "field_size",
+                self.field_size,
+                |size| (min_size..=max_size).contains(&size),
+                defaults.field_size,
+            ),
+            self_collision_grace: checked(
+                "self_collision_grace",
+                self.self_collision_grace,
+                |grace| grace >= 1,
+                defaults.self_collision_grace,
+            ),
+            head_grip: checked("head_grip", self.head_grip, positive, defaults.head_grip),
+            vision_radius: checked(
+                "vision_radius",
+                self.vision_radius,
+                positive,
+                defaults.vision_radius,
+            ),
+        }
+    }
+
+    /// Наименьший и наибольший радиус фрукта. Чем меньше фрукт, тем больше он стоит.
+    pub fn fruit_radius_range(&self) -> (f32, f32) {
+        (self.fruit_radius * SMALLEST_FRUIT_SHARE, self.fruit_radius)
+    }
+}
+
+/// Значение константы, если оно годится, иначе значение по умолчанию.
+/// Об отброшенном значении сообщаем, чтобы опечатку в файле было легко найти.
+fn checked<T: Copy + Display>(name: &str, value: T, valid: impl Fn(T) -> bool, default: T) -> T {
+    if valid(value) {
+        value
+    } else {
+        eprintln!("Ignoring invalid {name} = {value} in {CONFIG_FILE_NAME}, using {default}");
+        default
+    }
+}
+
+/// По умолчанию константы те же, с которыми игра задумывалась.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            init_speed: INIT_SPEED,
+            unit_radius: UNIT_RADIUS,
+            fruit_radius: FRUIT_RADIUS,
+            rotation_per_sec: ROTATION_PER_SEC,
+            field_size: FIELD_SIZE,
+            self_collision_grace: SELF_COLLISION_GRACE,
+            head_grip: HEAD_GRIP,
+            vision_radius: VISION_RADIUS,
+        }
+    }
+}
+
+/// Уровень сложности: насколько быстрая и вёрткая змейка и насколько велико поле.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    /// Змейка медленнее и плавнее поворачивает, а поле просторнее.
+    Easy,
+    /// Константы из `config.toml` как есть.
+    Normal,
+    /// Змейка быстрее и резче поворачивает, а поле теснее.
+    Hard,
+}
+
+impl Difficulty {
+    /// Все уровни сложности в порядке объявления.
+    pub const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    /// Название уровня сложности для меню.
+    pub fn name(self, strings: &Strings) -> &'static str {
+        strings.difficulties[self as usize]
+    }
+
+    /// Следующий уровень сложности по кругу.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// Предыдущий уровень сложности по кругу.
+    pub fn prev(self) -> Self {
+        Self::ALL[(self as usize + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Игровые константы для уровня сложности. Скорость, поворот и размер поля
+    /// меняются относительно констант обычного уровня `normal`.
+    pub fn config(self, normal: &Config) -> Config {
+        let normal = *normal;
+        let (speed, rotation, field) = match self {
+            Self::Easy => (0.75, 0.75, 1.25),
+            Self::Normal => return normal,
+            Self::Hard => (1.3, 1.25, 0.75),
+        };
+        Config {
+            init_speed: normal.init_speed * speed,
+            rotation_per_sec: normal.rotation_per_sec * rotation,
+            field_size: normal.field_size * field,
+            ..normal
+        }
+    }
+}
+
+/// Настройки игры, которые можно менять во время её работы.
+#[derive(Clone, Copy)]
+pub struct GameConfig {
+    /// Высота игрового поля. Ширина получается из неё и `field_aspect`.
+    pub field_size: f32,
+    /// Во сколько раз поле шире, чем выше. У квадратного поля это 1.
+    pub field_aspect: f32,
+    /// Если включено, то змейка не разбивается о стены, а появляется с противоположной стороны поля.
+    pub wrap_walls: bool,
+    /// Номер цветовой темы в `THEMES`. Выбор сохраняется до конца сеанса.
+    pub theme_index: usize,
+    /// Сколько сегментов у змейки в начале раунда, не считая головы.
+    pub starting_length: usize,
+    /// Камера, через которую поле выводится на экран.
+    pub camera: Camera,
+    /// Режим внезапной смерти: стены постепенно сдвигаются к центру поля.
+    pub sudden_death: bool,
+    /// Игра на время: успеть набрать как можно больше очков за `TIMED_ROUND_DURATION`.
+    pub timed: bool,
+    /// Голод: змейка, которая давно не ела, теряет сегменты, а без них умирает.
+    pub hunger: bool,
+    /// Игра на длину: победить, дорастив змейку до `TARGET_LENGTH`.
+    pub goal: bool,
+    /// Сколько секунд идёт текущий раунд.
+    pub round_time: f32,
+    /// Выбранный уровень сложности.
+    pub difficulty: Difficulty,
+    /// Константы из `config.toml`, прочитанные при запуске игры.
+    /// От них считаются константы каждого уровня сложности.
+    pub base_tuning: Config,
+    /// Игровые константы выбранного уровня сложности.
+    pub tuning: Config,
+    /// Играть ли звуки вроде поедания фрукта. Музыка включается отдельно.
+    pub sound_effects: bool,
+    /// Первый игрок поворачивает змейку к курсору мыши вместо клавиш.
+    pub mouse_steering: bool,
+    /// Рисовать ли на голове стрелку направления движения.
+    pub direction_arrow: bool,
+    /// Классический режим: змейка ходит по клеткам и поворачивает под прямым углом.
+    pub classic: bool,
+    /// Раскраска змеек. На игру не влияет.
+    pub skin: Skin,
+    /// Палитра для дальтоников вместо выбранной цветовой темы.
+    pub colorblind: bool,
+    /// Туман: видно только круг вокруг головы первого игрока. На саму игру не влияет,
+    /// фрукт в тумане съедается так же, как на виду.
+    pub fog: bool,
+    /// Номер языка надписей в `LANGUAGES`.
+    pub language: usize,
+}
+
+impl GameConfig {
+    /// Настройки по умолчанию с константами `base_tuning` и заданным уровнем сложности.
+    pub fn with_tuning(base_tuning: Config, difficulty: Difficulty) -> Self {
+        let mut config = Self {
+            base_tuning,
+            ..Self::default()
+        };
+        config.set_difficulty(difficulty);
+        config
+    }
+
+    /// Выбираем уровень сложности. Размер поля возвращается к размеру для этого уровня.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.tuning = difficulty.config(&self.base_tuning);
+        self.field_size = self.tuning.field_size;
+    }
+
+    /// Заново берём константы выбранного уровня сложности перед началом раунда.
+    /// Размер поля, выбранный в меню, при этом не меняется.
+    pub fn reset_tuning(&mut self) {
+        self.tuning = self.difficulty.config(&self.base_tuning);
+    }
+
+    /// Ширина и высота поля в начале раунда.
+    pub fn field_extents(&self) -> Vec2 {
+        Vec2::new(self.field_size * self.field_aspect, self.field_size)
+    }
+
+    /// Половина ширины и высоты поля: координаты внутри поля лежат от `-half_extents` до `half_extents`.
+    /// В режиме внезапной смерти учитываем, на сколько уже сдвинулись стены.
+    pub fn half_extents(&self) -> Vec2 {
+        self.field_extents() / 2.0 - self.shrink()
+    }
+
+    /// Половина меньшей стороны поля. Круг такого радиуса вокруг центра помещается в поле.
+    pub fn half_size(&self) -> f32 {
+        self.half_extents().min_element()
+    }
+
+    /// На сколько стены сдвинулись к центру с начала раунда.
+    /// Поле сжимается только в режиме внезапной смерти и не меньше чем до `MIN_HALF_SIZE`
+    /// по меньшей стороне.
+    fn shrink(&self) -> f32 {
+        if !self.sudden_death {
+            return 0.0;
+        }
+        let max_shrink = (self.field_extents().min_element() / 2.0 - MIN_HALF_SIZE).max(0.0);
+        (SHRINK_PER_SEC * self.round_time).min(max_shrink)
+    }
+
+    /// Сколько секунд осталось до конца раунда в игре на время.
+    pub fn time_left(&self) -> Option<f32> {
+        self.timed
+            .then(|| (TIMED_ROUND_DURATION - self.round_time).max(0.0))
+    }
+
+    /// До какой длины нужно дорасти в игре на длину.
+    pub fn target_length(&self) -> Option<u32> {
+        self.goal.then_some(TARGET_LENGTH)
+    }
+
+    /// Переключаем цель раунда по кругу: до смерти, на время и на длину.
+    pub fn next_round_goal(&mut self) {
+        (self.timed, self.goal) = match (self.timed, self.goal) {
+            (false, false) => (true, false),
+            (true, _) => (false, true),
+            (false, true) => (false, false),
+        };
+    }
+
+    /// Проходит ли змейка сквозь стены. В режиме внезапной смерти стены всегда сплошные.
+    pub fn walls_wrap(&self) -> bool {
+        self.wrap_walls && !self.sudden_death
+    }
+
+    /// Отсчитываем время раунда.
+    pub fn advance_round(&mut self, dt: f32) {
+        self.round_time += dt;
+    }
+
+    /// Ширина и высота видимой на экране части поля.
+    /// Высота не больше `MAX_VIEW_SIZE`, а ширина такая, чтобы сохранить форму поля.
+    pub fn view_extents(&self) -> Vec2 {
+        Vec2::new(self.field_aspect, 1.0) * self.field_size.min(MAX_VIEW_SIZE)
+    }
+
+    /// Помещается ли поле на экране целиком.
+    pub fn field_fits(&self) -> bool {
+        self.field_size <= MAX_VIEW_SIZE
+    }
+
+    /// Текущая цветовая тема. Палитра для дальтоников заменяет любую выбранную тему.
+    pub fn theme(&self) -> &'static Theme {
+        if self.colorblind {
+            &COLORBLIND_THEME
+        } else {
+            &THEMES[self.theme_index]
+        }
+    }
+
+    /// Переключаемся на следующую цветовую тему.
+    pub fn next_theme(&mut self) {
+        self.theme_index = (self.theme_index + 1) % THEMES.len();
+    }
+
+    /// Надписи на выбранном языке.
+    pub fn strings(&self) -> &'static Strings {
+        LANGUAGES[self.language]
+    }
+
+    /// Переключаемся на следующий язык надписей.
+    pub fn next_language(&mut self) {
+        self.language = (self.language + 1) % LANGUAGES.len();
+    }
+
+    /// Переключаемся на предыдущий язык надписей.
+    pub fn prev_language(&mut self) {
+        self.language = (self.language + LANGUAGES.len() - 1) % LANGUAGES.len();
+    }
+
+    /// Переключаемся на следующую стартовую длину змейки из `STARTING_LENGTHS`.
+    pub fn next_starting_length(&mut self) {
+        let next = STARTING_LENGTHS
+            .iter()
+            .position(|&length| length == self.starting_length)
+            .map_or(0, |index| (index + 1) % STARTING_LENGTHS.len());
+        self.starting_length = STARTING_LENGTHS[next];
+    }
+
+    /// Меняем размер поля на заданную величину, не выходя за допустимые пределы.
+    pub fn resize_field(&mut self, delta: f32) {
+        let (min_size, max_size) = FIELD_SIZE_RANGE;
+        self.field_size = (self.field_size + delta).clamp(min_size, max_size);
+    }
+
+    /// Переключаемся на следующую форму поля из `FIELD_ASPECTS`.
+    pub fn next_field_aspect(&mut self) {
+        let next = FIELD_ASPECTS
+            .iter()
+            .position(|&aspect| aspect == self.field_aspect)
+            .map_or(0, |index| (index + 1) % FIELD_ASPECTS.len());
+        self.field_aspect = FIELD_ASPECTS[next];
+    }
+}
+
+/// По умолчанию поле размером `FIELD_SIZE` со сплошными стенами.
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            field_size: FIELD_SIZE,
+            field_aspect: 1.0,
+            wrap_walls: false,
+            theme_index: 0,
+            starting_length: 0,
+            camera: Camera::default(),
+            sudden_death: false,
+            timed: false,
+            hunger: false,
+            goal: false,
+            round_time: 0.0,
+            difficulty: Difficulty::Normal,
+            base_tuning: Config::default(),
+            tuning: Config::default(),
+            sound_effects: true,
+            mouse_steering: false,
+            direction_arrow: true,
+            classic: false,
+            skin: Skin::Solid,
+            colorblind: false,
+            fog: false,
+            language: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::snake::Snake;
+
+    #[test]
+    fn field_size_changes_wall_distance() {
+        let mut config = GameConfig::default();
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(1.2, 0.0);
+        assert!(snake.death_cause(&config).is_some());
+
+        config.resize_field(FIELD_SIZE_STEP * 2.0);
+        assert!(snake.death_cause(&config).is_none());
+
+        // Размер поля не выходит за допустимые пределы.
+        config.resize_field(100.0);
+        assert_eq!(config.field_size, FIELD_SIZE_RANGE.1);
+    }
+
+    #[test]
+    fn wide_field_has_separate_walls() {
+        let config = GameConfig {
+            field_aspect: 2.0,
+            ..GameConfig::default()
+        };
+        assert_eq!(
+            config.half_extents(),
+            Vec2::new(FIELD_SIZE, FIELD_SIZE / 2.0)
+        );
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(1.2, 0.0);
+        assert!(snake.death_cause(&config).is_none());
+        snake.head.unit.position = Vec2::new(0.0, 1.2);
+        assert!(snake.death_cause(&config).is_some());
+    }
+
+    #[test]
+    fn shrinking_wall_catches_head() {
+        let mut config = GameConfig {
+            sudden_death: true,
+            wrap_walls: true,
+            ..GameConfig::default()
+        };
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(0.8, 0.0);
+        assert!(snake.death_cause(&config).is_none());
+
+        config.advance_round(20.0);
+        assert!(snake.death_cause(&config).is_some());
+
+        // Поле не сжимается меньше заданного предела.
+        config.advance_round(1000.0);
+        assert_eq!(config.half_size(), MIN_HALF_SIZE);
+    }
+
+    #[test]
+    fn timed_round_runs_out() {
+        let mut config = GameConfig::default();
+        config.advance_round(TIMED_ROUND_DURATION);
+        assert_eq!(config.time_left(), None);
+
+        config.timed = true;
+        config.round_time = 0.0;
+        config.advance_round(TIMED_ROUND_DURATION - 1.0);
+        assert_eq!(config.time_left(), Some(1.0));
+        config.advance_round(2.0);
+        assert_eq!(config.time_left(), Some(0.0));
+    }
+
+    #[test]
+    fn round_goals_cycle() {
+        let mut config = GameConfig::default();
+        assert_eq!(config.target_length(), None);
+        config.next_round_goal();
+        assert!(config.timed && !config.goal);
+        config.next_round_goal();
+        assert!(!config.timed && config.target_length() == Some(TARGET_LENGTH));
+        config.next_round_goal();
+        assert!(!config.timed && !config.goal);
+    }
+
+    #[test]
+    fn colorblind_palette_overrides_theme() {
+        let mut config = GameConfig::default();
+        config.next_theme();
+        let chosen = config.theme().id;
+        config.colorblind = true;
+        assert_eq!(config.theme().id, COLORBLIND_THEME.id);
+        assert!(config.theme().fruit_markers);
+
+        // Выбранная тема не теряется и возвращается, когда палитру выключают.
+        config.colorblind = false;
+        assert_eq!(config.theme().id, chosen);
+    }
+
+    #[test]
+    fn missing_constants_fall_back_to_defaults() {
+        let tuning = Config::from_toml("init_speed = 0.6\nfield_size = 3.0\n").unwrap();
+        assert_eq!(tuning.init_speed, 0.6);
+        assert_eq!(tuning.unit_radius, UNIT_RADIUS);
+
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+        assert!(Config::from_toml("init_speed = \"fast\"").is_err());
+        assert!(Config::from_toml("init_speed = ").is_err());
+    }
+
+    #[test]
+    fn out_of_range_constants_fall_back_to_defaults() {
+        let text = "unit_radius = 0.0\nfield_size = 0.5\ninit_speed = -1.0\n\
+                    rotation_per_sec = 2.0\nself_collision_grace = 0\n";
+        let tuning = Config::from_toml(text).unwrap();
+        assert_eq!(tuning.unit_radius, UNIT_RADIUS);
+        assert_eq!(tuning.field_size, FIELD_SIZE);
+        assert_eq!(tuning.init_speed, INIT_SPEED);
+        assert_eq!(tuning.self_collision_grace, SELF_COLLISION_GRACE);
+        // Годные значения остаются как есть.
+        assert_eq!(tuning.rotation_per_sec, 2.0);
+
+        let tuning = Config::from_toml("fruit_radius = nan\nhead_grip = inf\n").unwrap();
+        assert_eq!(tuning, Config::default());
+    }
+
+    #[test]
+    fn harder_difficulty_is_faster_and_tighter() {
+        let normal = Config::default();
+        let easy = Difficulty::Easy.config(&normal);
+        let hard = Difficulty::Hard.config(&normal);
+        assert_eq!(Difficulty::Normal.config(&normal), normal);
+        assert!(easy.init_speed < normal.init_speed && normal.init_speed < hard.init_speed);
+        assert!(easy.rotation_per_sec < hard.rotation_per_sec);
+        assert!(easy.field_size > hard.field_size);
+        assert_eq!(easy.unit_radius, hard.unit_radius);
+
+        // Размеры полей всех уровней можно выбрать и в меню.
+        let (min_size, max_size) = FIELD_SIZE_RANGE;
+        for size in [easy.field_size, hard.field_size] {
+            assert!((min_size..=max_size).contains(&size));
+        }
+        assert_eq!(Difficulty::Hard.next(), Difficulty::Easy);
+    }
+}
@@ -0,0 +1,543 @@
+//! Фрукты, которые собирает змейка.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, GameConfig};
+use crate::player::Player;
+use crate::random::{random_position_with, GlobalRng, RngSource};
+use crate::render::{ScreenTransform, Theme};
+use crate::save;
+
+/// Радиус самого крупного фрукта по умолчанию.
+pub const FRUIT_RADIUS: f32 = 0.08;
+
+/// Сколько фруктов одновременно лежит на поле.
+pub const FRUITS_COUNT: usize = 3;
+
+/// Вероятность того, что новый фрукт окажется золотым.
+pub const GOLDEN_FRUIT_CHANCE: f32 = 0.1;
+
+/// Вероятность того, что новый фрукт окажется фиолетовым и укоротит змейку.
+pub const SHRINKING_FRUIT_CHANCE: f32 = 0.1;
+
+/// Ближе этого расстояния до головы фрукты притягивает магнит.
+pub const MAGNET_RADIUS: f32 = 0.6;
+
+/// С какой скоростью магнит тянет фрукты к голове.
+pub const MAGNET_SPEED: f32 = 0.3;
+
+/// Разновидность фрукта.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FruitKind {
+    /// Обычный фрукт.
+    Normal,
+    /// Редкий золотой фрукт, который приносит больше очков.
+    Golden,
+    /// Бонусный фрукт, который ненадолго появляется сам по себе. Очень ценный.
+    Bonus,
+    /// Фиолетовый фрукт, который укорачивает змейку на один сегмент. Очков не приносит.
+    Shrinking,
+}
+
+impl FruitKind {
+    /// Случайная разновидность: золотые и фиолетовые фрукты появляются редко.
+    pub fn random(rng: &mut impl RngSource) -> Self {
+        let roll = rng.next_f32();
+        if roll < GOLDEN_FRUIT_CHANCE {
+            Self::Golden
+        } else if roll < GOLDEN_FRUIT_CHANCE + SHRINKING_FRUIT_CHANCE {
+            Self::Shrinking
+        } else {
+            Self::Normal
+        }
+    }
+
+    /// Сколько очков приносит фрукт.
+    pub fn value(self) -> u32 {
+        match self {
+            Self::Normal => 1,
+            Self::Golden => 5,
+            Self::Bonus => 25,
+            Self::Shrinking => 0,
+        }
+    }
+
+    /// Цвет фрукта в заданной теме.
+    pub fn color(self, theme: &Theme) -> Color {
+        match self {
+            Self::Normal => theme.fruit,
+            Self::Golden | Self::Bonus => theme.golden_fruit,
+            Self::Shrinking => theme.shrinking_fruit,
+        }
+    }
+
+    /// Значок поверх фрукта, по которому разновидность видна без цвета:
+    /// точка у обычного фрукта, ромб у ценного и черта у укорачивающего.
+    pub fn draw_marker(self, center: Vec2, radius_pixels: f32, alpha: f32) {
+        let color = Color::new(0.0, 0.0, 0.0, 0.7 * alpha);
+        let size = radius_pixels * 0.5;
+        match self {
+            Self::Normal => draw_circle(center.x, center.y, size * 0.5, color),
+            Self::Golden | Self::Bonus => draw_poly(center.x, center.y, 4, size, 0.0, color),
+            Self::Shrinking => {
+                let thickness = size * 0.5;
+                draw_line(
+                    center.x - size,
+                    center.y,
+                    center.x + size,
+                    center.y,
+                    thickness,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+/// Сколько раз пробуем подобрать для фрукта место, не занятое змейками.
+pub const FRUIT_SPAWN_ATTEMPTS: usize = 32;
+
+/// Через сколько секунд несъеденный фрукт переезжает на новое место.
+pub const FRUIT_MAX_AGE: f32 = 12.0;
+
+/// За сколько секунд до переезда фрукт начинает бледнеть.
+const FRUIT_FADE_TIME: f32 = 3.0;
+
+/// Насколько прозрачным становится фрукт перед самым переездом.
+const FRUIT_MIN_ALPHA: f32 = 0.3;
+
+/// На какую долю радиуса фрукт раздувается и сжимается, чтобы его было заметнее.
+pub const FRUIT_PULSE_AMPLITUDE: f32 = 0.08;
+
+/// Сколько раз в секунду фрукт раздувается.
+const FRUIT_PULSE_RATE: f64 = 1.2;
+
+/// Во сколько раз фрукт нарисован крупнее своего радиуса в момент `time`.
+/// Разные фрукты пульсируют не в такт, поэтому у каждого свой сдвиг `phase`.
+/// Съесть фрукт можно всё так же по его настоящему радиусу.
+pub fn pulse_scale(time: f64, phase: f32) -> f32 {
+    let angle = (time * FRUIT_PULSE_RATE * std::f64::consts::TAU) as f32 + phase;
+    1.0 + FRUIT_PULSE_AMPLITUDE * angle.sin()
+}
+
+/// Вероятность того, что новый фрукт будет дрейфовать по полю.
+pub const MOVING_FRUIT_CHANCE: f32 = 0.2;
+
+/// С какой скоростью дрейфует подвижный фрукт.
+pub const FRUIT_DRIFT_SPEED: f32 = 0.15;
+
+/// Фрукт, который можно собрать.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Fruit {
+    #[serde(with = "save::vec2")]
+    pub position: Vec2,
+    pub radius: f32,
+    pub kind: FruitKind,
+    /// Сколько секунд фрукт уже лежит на поле.
+    pub age: f32,
+    /// Скорость, с которой фрукт дрейфует по полю. Обычно фрукт лежит на месте.
+    #[serde(with = "save::vec2")]
+    pub velocity: Vec2,
+}
+
+impl Fruit {
+    /// Обычный неподвижный фрукт заданного размера в заданном месте.
+    pub fn at(position: Vec2, radius: f32) -> Self {
+        Self {
+            position,
+            radius,
+            kind: FruitKind::Normal,
+            age: 0.0,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    /// Фрукт будет появляться в случайном месте игрового поля, но не на змейках
+    /// и не на других фруктах `fruits`.
+    /// Если свободное место найти не удалось, то берём самое просторное из найденных.
+    pub fn respawn(config: &GameConfig, occupied: &[Vec2], fruits: &[Fruit]) -> Self {
+        Self::respawn_with(config, occupied, fruits, &mut GlobalRng)
+    }
+
+    /// То же, что `respawn`, но случайные числа берём из `rng`.
+    pub fn respawn_with(
+        config: &GameConfig,
+        occupied: &[Vec2],
+        fruits: &[Fruit],
+        rng: &mut impl RngSource,
+    ) -> Self {
+        let (min_radius, max_radius) = config.tuning.fruit_radius_range();
+        let radius = min_radius + rng.next_f32() * (max_radius - min_radius);
+        let position = free_position(config, occupied, fruits, radius, rng);
+        let velocity = if rng.next_f32() < MOVING_FRUIT_CHANCE {
+            let angle = rng.next_f32() * std::f32::consts::TAU;
+            Vec2::from_angle(angle) * FRUIT_DRIFT_SPEED
+        } else {
+            Vec2::ZERO
+        };
+        Self {
+            position,
+            radius,
+            kind: FruitKind::random(rng),
+            age: 0.0,
+            velocity,
+        }
+    }
+
+    /// Подвижный фрукт смещается и отскакивает от стен, не выходя за них даже краем.
+    pub fn update(&mut self, dt: f32, config: &GameConfig) {
+        if self.velocity == Vec2::ZERO {
+            return;
+        }
+        self.position += self.velocity * dt;
+
+        let limits = (config.half_extents() - self.radius).max(Vec2::ZERO);
+        for (coord, speed, limit) in [
+            (&mut self.position.x, &mut self.velocity.x, limits.x),
+            (&mut self.position.y, &mut self.velocity.y, limits.y),
+        ] {
+            if coord.abs() > limit {
+                *coord = coord.clamp(-limit, limit);
+                *speed = -speed.abs() * coord.signum();
+            }
+        }
+    }
+
+    /// Магнит тянет фрукт к голове змейки, если он ближе `MAGNET_RADIUS`.
+    /// За шаг фрукт сдвигается не дальше самой головы и не выходит за стены.
+    pub fn attract(&mut self, head: Vec2, dt: f32, config: &GameConfig) {
+        let to_head = head - self.position;
+        let distance = to_head.length();
+        if distance > MAGNET_RADIUS || distance == 0.0 {
+            return;
+        }
+        self.position += to_head / distance * (MAGNET_SPEED * dt).min(distance);
+        let limit = (config.half_extents() - self.radius).max(Vec2::ZERO);
+        self.position = self.position.clamp(-limit, limit);
+    }
+
+    /// Сколько очков приносит фрукт: маленькие фрукты стоят вдвое больше крупных.
+    pub fn value(&self, tuning: &Config) -> u32 {
+        let size_bonus = (tuning.fruit_radius / self.radius).round() as u32;
+        self.kind.value() * size_bonus
+    }
+
+    /// Фрукт стареет. Возвращаем, не пора ли переложить его на новое место.
+    pub fn grow_old(&mut self, dt: f32) -> bool {
+        self.age += dt;
+        self.age >= FRUIT_MAX_AGE
+    }
+
+    /// Фрукт будет отображаться в виде слегка пульсирующего круга цвета своей разновидности.
+    /// Незадолго до переезда на новое место фрукт бледнеет.
+    pub fn draw(&self, config: &GameConfig, screen: &ScreenTransform) {
+        let ppm = screen.pixels_per_meter;
+        let phase = (self.position.x + self.position.y) * 10.0;
+        let radius_pixels = self.radius * pulse_scale(get_time(), phase) * ppm;
+        let screen_pos = screen.to_screen(self.position);
+        let freshness = (FRUIT_MAX_AGE - self.age) / FRUIT_FADE_TIME;
+        let color = Color {
+            a: freshness.clamp(FRUIT_MIN_ALPHA, 1.0),
+            ..self.kind.color(config.theme())
+        };
+        draw_circle(screen_pos.x, screen_pos.y, radius_pixels, color);
+        if config.theme().fruit_markers {
+            self.kind.draw_marker(screen_pos, radius_pixels, color.a);
+        }
+    }
+}
+
+/// Случайное место для фрукта заданного радиуса, не занятое змейками и другими фруктами.
+/// Фрукты разносим не меньше чем на два радиуса фрукта, чтобы они не накладывались.
+/// Если свободное место найти не удалось, то берём самое просторное из найденных.
+fn free_position(
+    config: &GameConfig,
+    occupied: &[Vec2],
+    fruits: &[Fruit],
+    radius: f32,
+    rng: &mut impl RngSource,
+) -> Vec2 {
+    let snake_distance = radius + config.tuning.unit_radius;
+    let fruit_distance = 2.0 * config.tuning.fruit_radius;
+    // Насколько место просторнее, чем нужно. Отрицательное значение значит, что место занято.
+    let clearance = |position: Vec2| {
+        let from_snakes = occupied
+            .iter()
+            .map(|other| other.distance(position) - snake_distance);
+        let from_fruits = fruits
+            .iter()
+            .map(|other| other.position.distance(position) - fruit_distance);
+        from_snakes.chain(from_fruits).fold(f32::INFINITY, f32::min)
+    };
+
+    let mut position = random_position_with(config, rng);
+    let mut best_clearance = clearance(position);
+    for _ in 1..FRUIT_SPAWN_ATTEMPTS {
+        if best_clearance >= 0.0 {
+            break;
+        }
+        let candidate = random_position_with(config, rng);
+        let candidate_clearance = clearance(candidate);
+        if candidate_clearance > best_clearance {
+            position = candidate;
+            best_clearance = candidate_clearance;
+        }
+    }
+    position
+}
+
+/// Вероятность появления бонусного фрукта за секунду.
+pub const BONUS_FRUIT_CHANCE_PER_SEC: f32 = 0.05;
+
+/// Сколько секунд бонусный фрукт лежит на поле, прежде чем исчезнуть.
+pub const BONUS_FRUIT_LIFETIME: f32 = 5.0;
+
+/// Сколько раз в секунду пульсирует бонусный фрукт.
+const BONUS_FRUIT_PULSE_RATE: f32 = 3.0;
+
+/// Бонусный фрукт: появляется редко и исчезает, если его не успели съесть.
+pub struct BonusFruit {
+    pub fruit: Fruit,
+    /// Сколько секунд фрукт ещё пролежит на поле.
+    pub time_left: f32,
+}
+
+impl BonusFruit {
+    /// Бонусный фрукт появляется в случайном месте поля, но не на змейках и не на фруктах.
+    pub fn respawn(config: &GameConfig, occupied: &[Vec2], fruits: &[Fruit]) -> Self {
+        let radius = config.tuning.fruit_radius;
+        let position = free_position(config, occupied, fruits, radius, &mut GlobalRng);
+        let fruit = Fruit {
+            kind: FruitKind::Bonus,
+            ..Fruit::at(position, radius)
+        };
+        Self {
+            fruit,
+            time_left: BONUS_FRUIT_LIFETIME,
+        }
+    }
+
+    /// Отсчитываем время жизни фрукта. Возвращаем, лежит ли он ещё на поле.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.time_left -= dt;
+        self.time_left > 0.0
+    }
+
+    /// Бонусный фрукт пульсирует, чтобы его было сложно не заметить.
+    pub fn draw(&self, config: &GameConfig, screen: &ScreenTransform) {
+        let phase = self.time_left * BONUS_FRUIT_PULSE_RATE * std::f32::consts::TAU;
+        let pulse = 1.0 + 0.2 * phase.sin();
+        let radius_pixels = self.fruit.radius * pulse * screen.pixels_per_meter;
+        let screen_pos = screen.to_screen(self.fruit.position);
+        let color = self.fruit.kind.color(config.theme());
+        draw_circle(screen_pos.x, screen_pos.y, radius_pixels, color);
+        if config.theme().fruit_markers {
+            self.fruit.kind.draw_marker(screen_pos, radius_pixels, 1.0);
+        }
+    }
+}
+
+/// Позиции, занятые змейками всех игроков. На них не должны появляться фрукты.
+pub fn occupied_positions(players: &[Player]) -> Vec<Vec2> {
+    players
+        .iter()
+        .flat_map(|player| player.snake.segments())
+        .collect()
+}
+
+/// Раскладываем по полю новый набор фруктов так, чтобы они не лежали друг на друге.
+pub fn spawn_fruits(config: &GameConfig, players: &[Player]) -> Vec<Fruit> {
+    let occupied = occupied_positions(players);
+    let mut fruits = Vec::with_capacity(FRUITS_COUNT);
+    for _ in 0..FRUITS_COUNT {
+        let fruit = Fruit::respawn(config, &occupied, &fruits);
+        fruits.push(fruit);
+    }
+    fruits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::random::RNG_LOCK;
+    use crate::snake::{Snake, Unit, UNIT_RADIUS};
+
+    #[test]
+    fn same_seed_spawns_same_fruits() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let config = GameConfig::default();
+        let positions = |seed| {
+            rand::srand(seed);
+            (0..10)
+                .map(|_| Fruit::respawn(&config, &[], &[]).position)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(positions(7), positions(7));
+        assert_ne!(positions(7), positions(8));
+    }
+
+    #[test]
+    fn scripted_rng_gives_exact_fruit() {
+        let config = GameConfig::default();
+        let half = config.half_size();
+        let mut numbers = [0.0, 0.75, 0.25, 0.5, 0.99].into_iter();
+        let mut rng = || numbers.next().unwrap();
+        let fruit = Fruit::respawn_with(&config, &[], &[], &mut rng);
+        assert_eq!(fruit.position, Vec2::new(0.5 * half, -0.5 * half));
+        assert_eq!(fruit.radius, config.tuning.fruit_radius_range().0);
+        assert_eq!(fruit.velocity, Vec2::ZERO);
+        assert!(fruit.kind == FruitKind::Normal);
+
+        // Первое место занято змейкой, поэтому фрукт ложится во второе.
+        let mut numbers = [0.0, 0.5, 0.5, 1.0, 0.5, 0.0, 0.5, 0.0].into_iter();
+        let mut rng = || numbers.next().unwrap();
+        let fruit = Fruit::respawn_with(&config, &[Vec2::ZERO], &[], &mut rng);
+        assert_eq!(fruit.position, Vec2::new(half, 0.0));
+        assert!(fruit.velocity.length() > 0.0);
+        assert!(fruit.kind == FruitKind::Golden);
+    }
+
+    #[test]
+    fn fruit_does_not_spawn_on_snake() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        rand::srand(3);
+        let config = GameConfig::default();
+        // Змейка в виде горизонтальной линии через всё поле.
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(-config.half_size(), 0.0);
+        snake.units = (1..=(config.field_size / UNIT_RADIUS) as usize)
+            .map(|i| Unit {
+                position: Vec2::new(-config.half_size() + i as f32 * UNIT_RADIUS, 0.0),
+            })
+            .collect();
+        let occupied: Vec<_> = snake.segments().collect();
+
+        for _ in 0..200 {
+            let fruit = Fruit::respawn(&config, &occupied, &[]);
+            let distance = fruit.radius + snake.radius;
+            assert!(snake
+                .segments()
+                .all(|position| position.distance(fruit.position) >= distance));
+        }
+    }
+
+    #[test]
+    fn fruits_do_not_overlap_after_respawns() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        rand::srand(11);
+        let config = GameConfig::default();
+        let mut fruits = spawn_fruits(&config, &[]);
+        for i in 0..300 {
+            let index = i % fruits.len();
+            fruits[index] = Fruit::respawn(&config, &[], &fruits);
+        }
+
+        for (i, a) in fruits.iter().enumerate() {
+            for b in &fruits[i + 1..] {
+                assert!(a.position.distance(b.position) >= 2.0 * FRUIT_RADIUS);
+            }
+        }
+    }
+
+    #[test]
+    fn bonus_fruit_expires() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let config = GameConfig::default();
+        let mut bonus = BonusFruit::respawn(&config, &[], &[]);
+        assert!(bonus.fruit.kind == FruitKind::Bonus);
+        assert!(bonus.update(BONUS_FRUIT_LIFETIME - 0.1));
+        assert!(!bonus.update(0.2));
+    }
+
+    #[test]
+    fn old_fruit_needs_relocation() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let config = GameConfig::default();
+        let mut fruit = Fruit::respawn(&config, &[], &[]);
+        assert!(!fruit.grow_old(FRUIT_MAX_AGE - 1.0));
+        assert!(fruit.grow_old(1.0));
+        assert_eq!(Fruit::respawn(&config, &[], &[]).age, 0.0);
+    }
+
+    #[test]
+    fn magnet_pulls_fruit_without_overshooting() {
+        let config = GameConfig::default();
+        let mut fruit = Fruit::at(Vec2::new(0.3, 0.0), FRUIT_RADIUS);
+        fruit.attract(Vec2::ZERO, 0.1, &config);
+        assert!((fruit.position.x - (0.3 - MAGNET_SPEED * 0.1)).abs() < 1e-6);
+
+        // За долгий шаг фрукт доезжает до головы, но не проскакивает её.
+        fruit.attract(Vec2::ZERO, 10.0, &config);
+        assert_eq!(fruit.position, Vec2::ZERO);
+
+        // Далёкий фрукт магнит не трогает.
+        fruit.position = Vec2::new(MAGNET_RADIUS + 0.1, 0.0);
+        fruit.attract(Vec2::ZERO, 0.1, &config);
+        assert_eq!(fruit.position.x, MAGNET_RADIUS + 0.1);
+
+        // Голова у самой стены не вытягивает фрукт за неё.
+        let limit = config.half_size() - FRUIT_RADIUS;
+        fruit.position = Vec2::new(limit, 0.0);
+        fruit.attract(Vec2::new(config.half_size() + 0.05, 0.0), 1.0, &config);
+        assert_eq!(fruit.position.x, limit);
+    }
+
+    #[test]
+    fn pulse_stays_subtle() {
+        for step in 0..100 {
+            let scale = pulse_scale(step as f64 * 0.037, 1.0);
+            assert!((scale - 1.0).abs() <= FRUIT_PULSE_AMPLITUDE + 1e-6);
+        }
+        assert_eq!(pulse_scale(0.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn moving_fruit_bounces_inside_field() {
+        let config = GameConfig::default();
+        let limit = config.half_size() - FRUIT_RADIUS;
+        let mut fruit = Fruit {
+            velocity: Vec2::new(FRUIT_DRIFT_SPEED, FRUIT_DRIFT_SPEED / 2.0),
+            ..Fruit::at(Vec2::new(limit - 0.01, 0.0), FRUIT_RADIUS)
+        };
+        fruit.update(0.1, &config);
+        assert_eq!(fruit.position.x, limit);
+        assert!(fruit.velocity.x < 0.0);
+
+        for _ in 0..1000 {
+            fruit.update(0.1, &config);
+            assert!(fruit.position.x.abs() <= limit && fruit.position.y.abs() <= limit);
+        }
+        assert_eq!(
+            fruit.velocity.length(),
+            Vec2::new(1.0, 0.5).length() * FRUIT_DRIFT_SPEED
+        );
+
+        // Неподвижный фрукт так и лежит на месте.
+        let mut still = Fruit {
+            velocity: Vec2::ZERO,
+            ..fruit
+        };
+        let position = still.position;
+        still.update(1.0, &config);
+        assert_eq!(still.position, position);
+    }
+
+    #[test]
+    fn small_fruit_is_worth_more() {
+        let fruit = |radius| Fruit::at(Vec2::ZERO, radius);
+        let tuning = Config::default();
+        let (min_radius, max_radius) = tuning.fruit_radius_range();
+        assert_eq!(fruit(max_radius).value(&tuning), 1);
+        assert_eq!(fruit(min_radius).value(&tuning), 2);
+
+        // Столкновение считается по радиусу самого фрукта.
+        let mut snake = Snake::default();
+        let gap = UNIT_RADIUS + (min_radius + max_radius) / 2.0;
+        snake.head.unit.position = Vec2::new(-gap, 0.0);
+        assert!(snake.can_eat(&fruit(max_radius)));
+        assert!(!snake.can_eat(&fruit(min_radius)));
+    }
+}
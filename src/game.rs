@@ -0,0 +1,972 @@
+//! Игровой цикл и состояния игры.
+
+use macroquad::audio::play_sound_once;
+use macroquad::prelude::*;
+
+use std::fmt::Display;
+use std::fs;
+use std::iter;
+use std::mem;
+
+use crate::audio::{load_optional_sound, Music};
+use crate::config::{Config, Difficulty, GameConfig, FIELD_SIZE_STEP, TARGET_LENGTH};
+use crate::lang::{fill, startup_language, ENGLISH};
+use crate::leaderboard::{type_name, Entry, Leaderboard};
+use crate::player::{Gamepad, KeyBindings, Player, PlayersMode};
+use crate::power_up::PowerUpKind;
+use crate::random::{rng_seed, SEED_ENV_VAR};
+use crate::render::{
+    draw_centered_text, draw_clock_icon, draw_collision_overlay, draw_field, draw_fog,
+    draw_formatted, draw_grid, draw_hunger_bar, draw_menu_option, draw_minimap, draw_stamina_bar,
+    load_optional_texture, shake_offset, to_world_coords, FpsCounter, ScreenTransform,
+    SHAKE_DURATION,
+};
+use crate::replay::{PlayerInput, Replay, ReplayFrame};
+use crate::save::{SavedGame, SAVE_FILE_NAME};
+use crate::simulation::{FixedStep, Game, FIXED_DT};
+use crate::snapshot::Snapshot;
+use crate::state::{transition, GameState, RoundAction, StateInput, Transition};
+use crate::storage::data_file_path;
+
+/// Сколько секунд показывается каждая надпись обратного отсчёта.
+pub const COUNTDOWN_STEP: f32 = 0.7;
+
+/// Дольше стольких секунд кадр не считаем. Если окно перетаскивали или система надолго
+/// задумалась, то игра просто продолжается, а не прыгает вперёд на всё пропущенное время.
+pub const MAX_FRAME_TIME: f32 = 0.1;
+
+/// Клавиша, которая в покадровом режиме делает один шаг игры.
+/// Ни одному игроку она не нужна, поэтому шаг не случается от поворота.
+pub const FRAME_STEP_KEY: KeyCode = KeyCode::F3;
+
+/// Игровой цикл.
+pub async fn run() {
+    // Сообщаем зерно, чтобы запуск можно было повторить, задав его в `SNAKE_SEED`.
+    let seed = rng_seed();
+    eprintln!("Random seed: {seed} (set {SEED_ENV_VAR}={seed} to replay this run)");
+    rand::srand(seed);
+
+    // Размер поля и режим стен меняются в меню и между раундами.
+    // Играть можно одному, вдвоём на одной клавиатуре или против компьютера. Выбирается в меню.
+    // Константы из `config.toml` читаем один раз, чтобы записи раундов повторялись точно.
+    let mut config = GameConfig::with_tuning(Config::load(), Difficulty::Normal);
+    // Язык надписей берём из системы, а поменять его можно в настройках.
+    config.language = startup_language();
+    let mut game = Game::new(config, PlayersMode::Single);
+    let mut state = GameState::Menu;
+    // Затемнение при смене экрана, если оно сейчас идёт.
+    let mut fade: Option<Transition> = None;
+    let mut fixed_step = FixedStep::default();
+    // Сколько ещё трястись экрану после того, как змейка разбилась.
+    let mut shake = 0.0;
+    let shake_rng = rand::RandGenerator::new();
+    shake_rng.srand(seed);
+    let mut leaderboard = Leaderboard::load();
+    // Имя, которое игрок набирает для таблицы рекордов.
+    let mut player_name = String::new();
+    let mut gamepad = Gamepad::new();
+    // Запись текущего или последнего сыгранного раунда.
+    let mut replay = Replay::new(seed, &game.config, game.mode);
+    // Раунд, продолженный из сохранения, не записываем: его начало по зерну не повторить.
+    let mut recording = true;
+    // Недоигранный раунд, который можно продолжить из меню.
+    let save_path = data_file_path(SAVE_FILE_NAME);
+    let mut has_save = save_path.as_ref().is_some_and(|path| path.exists());
+    // Сетку поверх поля включают клавишей G.
+    let mut show_grid = false;
+    // Отладочные контуры столкновений включают клавишей F1.
+    let mut show_collisions = false;
+    // Покадровый режим для отладки движения: игра стоит, пока не нажата F3,
+    // и по каждому нажатию делает ровно один шаг. Включается клавишей F2 в отладочной сборке.
+    let mut frame_step = false;
+    // Миникарту на большом поле можно спрятать клавишей N.
+    let mut show_minimap = true;
+    // Счётчик кадров включают клавишей F.
+    let mut fps_counter = FpsCounter::new();
+    let mut show_fps = false;
+    // Строка для текста, который меняется каждый кадр. Память под неё выделяется один раз.
+    let mut hud_text = String::new();
+    let pause_key = KeyBindings::default().pause;
+    // Звук поедания фрукта загружаем один раз, до начала игры.
+    let chomp_sound = load_optional_sound("assets/chomp.wav").await;
+    let mut music = Music::new(load_optional_sound("assets/music.wav").await);
+    // Текстура головы змейки необязательна: без неё голова рисуется кругом с глазами.
+    let head_skin = load_optional_texture("assets/head.png").await;
+    // Игровой цикл
+    loop {
+        // Время кадра для игры. Счётчик кадров дальше берёт настоящее время кадра.
+        let frame_time = get_frame_time().min(MAX_FRAME_TIME);
+
+        // Очищаем экран, заполняя его цветом фона темы.
+        clear_background(game.config.theme().background);
+
+        // Ставим игру на паузу или снимаем с паузы.
+        // Во время затемнения ввод не принимаем, чтобы не сменить экран дважды.
+        let fading = fade.is_some();
+        let previous = state;
+        // Что сделать с раундом, когда сменится экран.
+        let mut round_action = None;
+        if !fading && (is_key_pressed(pause_key) || is_key_pressed(KeyCode::Escape)) {
+            state = transition(state, StateInput::Pause);
+        }
+        // Просмотр записи останавливаем по Escape.
+        if !fading && matches!(state, GameState::Replay { .. }) && is_key_pressed(KeyCode::Escape) {
+            state = transition(state, StateInput::ToMenu);
+        }
+
+        // Пока игрок набирает имя, буквы не должны переключать настройки.
+        let typing = matches!(state, GameState::NameEntry { .. });
+        let hotkey = |key| !typing && is_key_pressed(key);
+
+        // Включаем или выключаем музыку.
+        if hotkey(KeyCode::M) {
+            music.toggle_mute();
+        }
+
+        // Включаем или выключаем счётчик кадров.
+        // Время кадра учитываем всегда, чтобы при включении сразу показать верное число.
+        fps_counter.update(get_frame_time());
+        if hotkey(KeyCode::F) {
+            show_fps = !show_fps;
+        }
+
+        // Включаем или выключаем сетку.
+        if hotkey(KeyCode::G) {
+            show_grid = !show_grid;
+        }
+
+        // Включаем или выключаем контуры столкновений.
+        if hotkey(KeyCode::F1) {
+            show_collisions = !show_collisions;
+        }
+
+        if cfg!(debug_assertions) && hotkey(KeyCode::F2) {
+            frame_step = !frame_step;
+        }
+
+        // Включаем или выключаем миникарту.
+        if hotkey(KeyCode::N) {
+            show_minimap = !show_minimap;
+        }
+
+        // Печатаем снимок раунда одной строкой JSON. На игру это не влияет.
+        if hotkey(KeyCode::J) {
+            println!("{}", Snapshot::new(&game).to_json());
+        }
+
+        // Переключаем цветовую тему и перекрашиваем змеек.
+        if hotkey(KeyCode::T) {
+            game.config.next_theme();
+            game.recolor_snakes();
+        }
+
+        // Запись закончилась раньше, чем раунд: останавливаем просмотр.
+        if let (false, GameState::Replay { frame }) = (fading, state) {
+            if frame >= replay.frames.len() {
+                let ended = StateInput::RoundEnded {
+                    score: game.score(),
+                    won: None,
+                    qualifies: false,
+                };
+                state = transition(state, ended);
+            }
+        }
+
+        match state {
+            // Пока идёт затемнение, игра замирает.
+            _ if fading => {}
+            // В меню змейка стоит на месте, пока игрок не нажмёт Enter.
+            GameState::Menu => {
+                let config = &mut game.config;
+                if is_key_pressed(KeyCode::W) {
+                    config.wrap_walls = !config.wrap_walls;
+                }
+                if is_key_pressed(KeyCode::S) {
+                    config.sudden_death = !config.sudden_death;
+                }
+                if is_key_pressed(KeyCode::H) {
+                    config.hunger = !config.hunger;
+                }
+                if is_key_pressed(KeyCode::C) {
+                    config.next_round_goal();
+                }
+                if is_key_pressed(KeyCode::L) {
+                    config.next_starting_length();
+                    game.respawn_players();
+                }
+                if is_key_pressed(KeyCode::D) {
+                    game.set_difficulty(game.config.difficulty.next());
+                }
+                if is_key_pressed(KeyCode::O) {
+                    state = transition(state, StateInput::OpenSettings);
+                }
+                if is_key_pressed(KeyCode::A) {
+                    game.config.next_field_aspect();
+                    game.respawn_field();
+                }
+                let modes = [
+                    (KeyCode::Key1, PlayersMode::Single),
+                    (KeyCode::Key2, PlayersMode::TwoPlayers),
+                    (KeyCode::Key3, PlayersMode::VersusAi),
+                ];
+                for (key, mode) in modes {
+                    if is_key_pressed(key) {
+                        game.mode = mode;
+                        game.respawn_players();
+                    }
+                }
+
+                // Меняем размер поля и раскладываем фрукты и препятствия заново под новый размер.
+                let grow = is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd);
+                let shrink = is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract);
+                if grow || shrink {
+                    let delta = if grow {
+                        FIELD_SIZE_STEP
+                    } else {
+                        -FIELD_SIZE_STEP
+                    };
+                    game.config.resize_field(delta);
+                    game.respawn_field();
+                }
+
+                // Каждый раунд начинаем со своего зерна, чтобы его можно было записать.
+                if is_key_pressed(KeyCode::Enter) {
+                    let round_seed = rand::rand() as u64;
+                    game.start_round(round_seed);
+                    replay = Replay::new(round_seed, &game.config, game.mode);
+                    recording = true;
+                    state = transition(state, StateInput::Start);
+                }
+
+                // Продолжаем недоигранный раунд. Сохранение можно продолжить только один раз.
+                if has_save && is_key_pressed(KeyCode::Space) {
+                    has_save = false;
+                    let saved = save_path.as_deref().and_then(SavedGame::load);
+                    if let (Some(saved), Some(path)) = (saved, &save_path) {
+                        saved.restore(&mut game);
+                        recording = false;
+                        state = transition(state, StateInput::Start);
+                        if let Err(e) = fs::remove_file(path) {
+                            eprintln!("Failed to remove saved game {}: {e}", path.display());
+                        }
+                    }
+                }
+
+                // Смотрим сохранённую запись, если она есть.
+                if is_key_pressed(KeyCode::R) {
+                    if let Some(loaded) = Replay::load() {
+                        replay = loaded;
+                        replay.apply_to(&mut game.config);
+                        game.mode = replay.mode;
+                        game.start_round(replay.seed);
+                        recording = true;
+                        state = transition(state, StateInput::WatchReplay);
+                    }
+                }
+            }
+            // Строки выбираем стрелками вверх и вниз, а значение меняем стрелками влево и вправо.
+            // В меню возвращаемся по Escape или выбрав "Back".
+            GameState::Settings { selected } => {
+                let count = ENGLISH.settings.len();
+                if is_key_pressed(KeyCode::Up) {
+                    state = GameState::Settings {
+                        selected: (selected + count - 1) % count,
+                    };
+                }
+                if is_key_pressed(KeyCode::Down) {
+                    state = GameState::Settings {
+                        selected: (selected + 1) % count,
+                    };
+                }
+                let left = is_key_pressed(KeyCode::Left);
+                let right = is_key_pressed(KeyCode::Right);
+                let enter = is_key_pressed(KeyCode::Enter);
+                match selected {
+                    0 if left || right || enter => music.toggle_mute(),
+                    1 if left || right || enter => {
+                        game.config.sound_effects = !game.config.sound_effects;
+                    }
+                    2 if left => game.set_difficulty(game.config.difficulty.prev()),
+                    2 if right || enter => game.set_difficulty(game.config.difficulty.next()),
+                    3 if left || right || enter => {
+                        game.config.mouse_steering = !game.config.mouse_steering;
+                    }
+                    4 if left || right || enter => {
+                        game.config.direction_arrow = !game.config.direction_arrow;
+                    }
+                    // Режим движения меняется только между раундами, поэтому змеек ставим заново.
+                    5 if left || right || enter => {
+                        game.config.classic = !game.config.classic;
+                        game.respawn_players();
+                    }
+                    6 if left => game.config.skin = game.config.skin.prev(),
+                    6 if right || enter => game.config.skin = game.config.skin.next(),
+                    7 if left || right || enter => {
+                        game.config.colorblind = !game.config.colorblind;
+                        game.recolor_snakes();
+                    }
+                    8 if left || right || enter => game.config.fog = !game.config.fog,
+                    9 if left => game.config.prev_language(),
+                    9 if right || enter => game.config.next_language(),
+                    10 if enter => state = transition(state, StateInput::ToMenu),
+                    _ => {}
+                }
+                if is_key_pressed(KeyCode::Escape) {
+                    state = transition(state, StateInput::ToMenu);
+                }
+            }
+            // Во время отсчёта змейки стоят на месте, а фрукты нельзя съесть.
+            GameState::Countdown { .. } => {
+                let tick = StateInput::CountdownTick {
+                    dt: frame_time,
+                    duration: COUNTDOWN_STEP * ENGLISH.countdown.len() as f32,
+                };
+                state = transition(state, tick);
+            }
+            GameState::Playing | GameState::Replay { .. } => {
+                // Время кадра копим только во время игры, чтобы после паузы змейки не прыгали.
+                // Игра идёт целыми шагами одной длины, а остаток ждёт следующего кадра.
+                // В покадровом режиме делаем по шагу на каждое нажатие `FRAME_STEP_KEY`.
+                let steps = if frame_step {
+                    usize::from(is_key_pressed(FRAME_STEP_KEY))
+                } else {
+                    fixed_step.advance(frame_time)
+                };
+
+                // Первым игроком можно управлять и с геймпада.
+                // Если стик отклонён, то поворачиваем пропорционально отклонению.
+                // Стик влево поворачивает налево, то есть против часовой стрелки.
+                // Если в настройках включена мышь, то первая змейка поворачивает к курсору.
+                // В классическом режиме стик и мышь поворачивают змейку отдельными нажатиями.
+                let stick = gamepad.steering();
+                let cursor = to_world_coords(Vec2::from(mouse_position()), &game.config);
+
+                for _ in 0..steps {
+                    // Во время игры берём управление змейками из ввода и записываем его,
+                    // а при просмотре записи - из записанного кадра.
+                    let ReplayFrame { dt, inputs } = match state {
+                        GameState::Replay { frame } => match replay.frames.get(frame) {
+                            Some(frame) => frame.clone(),
+                            None => break,
+                        },
+                        _ => {
+                            let inputs = game
+                                .players
+                                .iter()
+                                .enumerate()
+                                .map(|(index, player)| {
+                                    let config = &game.config;
+                                    let smooth = if index == 0 && stick != 0.0 {
+                                        Some(-stick * config.tuning.rotation_per_sec)
+                                    } else if index == 0 && config.mouse_steering {
+                                        Some(player.snake.rotation_towards(cursor, config))
+                                    } else {
+                                        None
+                                    };
+                                    let rotation = match smooth {
+                                        Some(r) if config.classic => {
+                                            player.snake.grid_turn(r, config)
+                                        }
+                                        Some(r) => r,
+                                        None => player.rotation(&game.fruits, config),
+                                    };
+                                    let boost = player.wants_boost();
+                                    PlayerInput { rotation, boost }
+                                })
+                                .collect();
+                            let frame = ReplayFrame {
+                                dt: FIXED_DT,
+                                inputs,
+                            };
+                            if recording {
+                                replay.frames.push(frame.clone());
+                            }
+                            frame
+                        }
+                    };
+
+                    let result = game.step(&inputs, dt);
+                    if frame_step {
+                        let head = &game.players[0].snake.head;
+                        eprintln!(
+                            "Step: head {:?}, direction {:?}",
+                            head.position(),
+                            head.direction
+                        );
+                    }
+                    if result.ate_fruit && game.config.sound_effects {
+                        if let Some(sound) = &chomp_sound {
+                            play_sound_once(sound);
+                        }
+                    }
+
+                    // Если играют вдвоём, то оставшийся игрок побеждает.
+                    // Запоминаем очки в момент поражения, чтобы они не сбросились на экране.
+                    // Рекорд при просмотре записи не обновляем.
+                    if game.players.iter().any(Player::lost) {
+                        shake = SHAKE_DURATION;
+                    }
+                    if result.ended {
+                        let score = game.score();
+                        // Если результат попал в таблицу рекордов, то сначала спрашиваем имя.
+                        let ended = StateInput::RoundEnded {
+                            score,
+                            won: result.won.then_some(game.config.round_time),
+                            qualifies: leaderboard.rank(score).is_some(),
+                        };
+                        state = transition(state, ended);
+                        break;
+                    } else if let GameState::Replay { frame } = state {
+                        state = GameState::Replay { frame: frame + 1 };
+                    }
+                }
+            }
+            // На паузе ничего не обновляем, только рисуем застывший кадр и меню паузы.
+            // Пункты выбираем стрелками, а по Enter продолжаем игру или бросаем раунд и выходим в меню.
+            // По S сохраняем недоигранный раунд и выходим в меню.
+            // Доиграть его можно будет и после перезапуска игры.
+            GameState::Paused { selected } => {
+                let count = ENGLISH.pause_options.len();
+                if is_key_pressed(KeyCode::Up) {
+                    state = GameState::Paused {
+                        selected: (selected + count - 1) % count,
+                    };
+                }
+                if is_key_pressed(KeyCode::Down) {
+                    state = GameState::Paused {
+                        selected: (selected + 1) % count,
+                    };
+                }
+                if is_key_pressed(KeyCode::Enter) {
+                    state = if selected == 0 {
+                        transition(state, StateInput::Pause)
+                    } else {
+                        round_action = Some(RoundAction::NewRound);
+                        transition(state, StateInput::ToMenu)
+                    };
+                }
+                if let (true, Some(path)) = (is_key_pressed(KeyCode::S), &save_path) {
+                    SavedGame::new(&game).save(path);
+                    has_save = true;
+                    round_action = Some(RoundAction::NewRound);
+                    state = transition(state, StateInput::ToMenu);
+                }
+            }
+            // Имя подтверждаем по Enter. В таблицу рекордов его записываем сразу,
+            // а перезапуск по Enter начнёт слушаться только со следующего нажатия.
+            GameState::NameEntry { score } => {
+                type_name(&mut player_name, iter::from_fn(get_char_pressed));
+                if is_key_pressed(KeyCode::Backspace) {
+                    player_name.pop();
+                }
+                if is_key_pressed(KeyCode::Enter) {
+                    let name = mem::take(&mut player_name);
+                    leaderboard.insert(Entry { name, score });
+                    leaderboard.save();
+                    state = transition(state, StateInput::NameEntered);
+                }
+            }
+            // Пока экран трясётся после поражения, нажатия не принимаем.
+            GameState::GameOver { .. } if shake > 0.0 => {}
+            // После поражения или победы начинаем заново только по нажатию Enter
+            // или возвращаемся в меню по Escape, чтобы поменять настройки.
+            // По R пересматриваем последний раунд, а по S сохраняем его запись в файл,
+            // если раунд записывался.
+            // Раунд меняется в самой тёмной точке затемнения, а до неё видны его итоги.
+            GameState::GameOver { .. } | GameState::Won { .. } => {
+                let restart = is_key_pressed(KeyCode::Enter);
+                let to_menu = is_key_pressed(KeyCode::Escape);
+                let watch_replay = recording && is_key_pressed(KeyCode::R);
+                if restart || to_menu {
+                    round_action = Some(RoundAction::NewRound);
+                    let input = if restart {
+                        StateInput::Start
+                    } else {
+                        StateInput::ToMenu
+                    };
+                    state = transition(state, input);
+                } else if watch_replay {
+                    round_action = Some(RoundAction::WatchReplay);
+                    state = transition(state, StateInput::WatchReplay);
+                }
+                if recording && is_key_pressed(KeyCode::S) {
+                    replay.save();
+                }
+            }
+        }
+
+        // На другой экран переходим через затемнение: пока экран гаснет, показываем старый.
+        // Без затемнения раунд меняем сразу.
+        if !fading {
+            fade = Transition::between(previous, state).map(|f| f.with_action(round_action));
+            if fade.is_none() {
+                if let Some(action) = round_action {
+                    apply_round_action(action, &mut game, &mut replay, &mut recording);
+                }
+            }
+        }
+        if let Some(current) = &mut fade {
+            let running = current.update(frame_time);
+            state = current.state();
+            if let Some(action) = current.take_action() {
+                apply_round_action(action, &mut game, &mut replay, &mut recording);
+            }
+            if !running {
+                fade = None;
+            }
+        }
+
+        music.update(state);
+
+        // Если поле не помещается на экране, то камера следует за головой первого игрока.
+        let field_fits = game.config.field_fits();
+        let target = game.players[0].snake.head.position();
+        game.config.camera.follow(target, frame_time, field_fits);
+        shake = (shake - frame_time).max(0.0);
+        game.config.camera.shake_offset = shake_offset(shake, &shake_rng);
+
+        // Дальше только рисуем, ничего в игре не меняя.
+        let Game {
+            config,
+            mode,
+            players,
+            fruits,
+            obstacles,
+            bombs,
+            speed_pads,
+            portal,
+            power_up,
+            bonus,
+            particles,
+            floating_texts,
+            level,
+            level_banner,
+            near_miss,
+        } = &game;
+
+        // Перевод координат поля в координаты окна один на весь кадр.
+        let screen = ScreenTransform::for_window(config);
+        draw_field(config, &screen);
+        if show_grid {
+            draw_grid(config, &screen);
+        }
+        // Площадки лежат на полу, поэтому змейки проезжают поверх них.
+        for pad in speed_pads {
+            pad.draw(config, &screen);
+        }
+        for obstacle in obstacles {
+            obstacle.draw(config, &screen);
+        }
+        if portal.is_open(config) {
+            portal.draw(config, &screen);
+        }
+        for bomb in bombs {
+            bomb.draw(&screen);
+        }
+        // Отображаем змеек.
+        for player in players {
+            player.snake.draw(config, &screen, head_skin.as_ref());
+        }
+        // Отображаем фрукты.
+        for fruit in fruits {
+            fruit.draw(config, &screen);
+        }
+        if let Some(bonus) = &bonus {
+            bonus.draw(config, &screen);
+        }
+        if let Some(power_up) = &power_up {
+            power_up.draw(&screen);
+        }
+        for particle in particles {
+            particle.draw(&screen);
+        }
+        for text in floating_texts {
+            text.draw(&screen);
+        }
+        if show_collisions {
+            draw_collision_overlay(players, fruits, &screen);
+        }
+        // Туман закрывает поле, но не интерфейс. Миникарта выдала бы всё, что он прячет.
+        if config.fog {
+            let head = screen.to_screen(players[0].snake.head.position());
+            draw_fog(head, config.tuning.vision_radius * screen.pixels_per_meter);
+        }
+
+        // Миникарта нужна, только когда поле не помещается на экране.
+        if show_minimap && !field_fits && !config.fog {
+            draw_minimap(config, players, fruits);
+        }
+
+        // Весь текст рисуем цветом текущей темы и на выбранном языке.
+        let text_color = config.theme().text;
+        let strings = config.strings();
+
+        // Отображаем количество очков и длину змейки каждого игрока.
+        // Очки не совпадают с длиной змейки, поэтому показываем её отдельно.
+        let mut hud_y = 20.0;
+        for (index, player) in players.iter().enumerate() {
+            let position = Vec2::new(20.0, hud_y);
+            if players.len() == 1 {
+                let args: [&dyn Display; 1] = [&player.score];
+                draw_formatted(
+                    &mut hud_text,
+                    strings.scores,
+                    &args,
+                    position,
+                    24.0,
+                    text_color,
+                );
+            } else {
+                let args: [&dyn Display; 2] = [&(index + 1), &player.score];
+                let template = strings.player_line;
+                draw_formatted(&mut hud_text, template, &args, position, 24.0, text_color);
+            }
+            let args: [&dyn Display; 1] = [&player.snake.length()];
+            let position = Vec2::new(20.0, hud_y + 24.0);
+            draw_formatted(
+                &mut hud_text,
+                strings.length,
+                &args,
+                position,
+                24.0,
+                text_color,
+            );
+            let args: [&dyn Display; 1] = [&player.combo];
+            let position = Vec2::new(20.0, hud_y + 48.0);
+            draw_formatted(
+                &mut hud_text,
+                strings.combo,
+                &args,
+                position,
+                24.0,
+                text_color,
+            );
+            draw_stamina_bar(Vec2::new(20.0, hud_y + 58.0), player, text_color);
+            hud_y += 88.0;
+            if config.hunger {
+                draw_hunger_bar(Vec2::new(20.0, hud_y - 18.0), player, text_color);
+                hud_y += 12.0;
+            }
+        }
+        let args: [&dyn Display; 1] = [&leaderboard.best()];
+        let position = Vec2::new(20.0, hud_y);
+        draw_formatted(
+            &mut hud_text,
+            strings.best,
+            &args,
+            position,
+            24.0,
+            text_color,
+        );
+        if let Some(time_left) = config.time_left() {
+            let args: [&dyn Display; 1] = [&format_args!("{:02}", time_left.ceil() as u32)];
+            let position = Vec2::new(20.0, hud_y + 24.0);
+            draw_formatted(
+                &mut hud_text,
+                strings.time_left,
+                &args,
+                position,
+                24.0,
+                text_color,
+            );
+        }
+        if let Some(target) = config.target_length() {
+            let goal_text = fill(strings.goal, &[&target]);
+            draw_text(&goal_text, 20.0, hud_y + 24.0, 24.0, text_color);
+        }
+
+        // Пока у кого-нибудь действует замедление или игра замедлилась после почти
+        // столкновения, показываем часы.
+        let slow_motion = players
+            .iter()
+            .any(|p| p.has_effect(PowerUpKind::SlowMotion));
+        if slow_motion || near_miss.time_left > 0.0 {
+            draw_clock_icon(Vec2::new(screen_width() - 40.0, 40.0), 20.0);
+        }
+        // Перевёрнутое управление легко не заметить, поэтому предупреждаем о нём крупно.
+        let reversed = players
+            .iter()
+            .any(|p| p.has_effect(PowerUpKind::ReverseControls));
+        if reversed {
+            let color = PowerUpKind::ReverseControls.color();
+            draw_centered_text(strings.controls_reversed, 80.0, 32.0, color);
+        }
+        if *level_banner > 0.0 {
+            let level_text = fill(strings.level, &[level]);
+            draw_centered_text(&level_text, screen_height() / 3.0, 48.0, text_color);
+        }
+        if show_fps {
+            let args: [&dyn Display; 1] = [&format_args!("{:.0}", fps_counter.fps())];
+            let position = Vec2::new(20.0, screen_height() - 20.0);
+            draw_formatted(
+                &mut hud_text,
+                strings.fps,
+                &args,
+                position,
+                24.0,
+                text_color,
+            );
+        }
+        if frame_step {
+            let head = &players[0].snake.head;
+            let (position, direction) = (head.position(), head.direction);
+            let coordinate = |value: f32| format!("{value:.4}");
+            let coordinates = [position.x, position.y, direction.x, direction.y].map(coordinate);
+            let args = coordinates.each_ref().map(|c| c as &dyn Display);
+            let step_text = fill(strings.frame_step, &args);
+            draw_text(&step_text, 20.0, screen_height() - 50.0, 24.0, text_color);
+        }
+
+        match state {
+            GameState::Menu => {
+                let center = screen_height() / 2.0;
+                draw_centered_text(strings.title, center, 64.0, text_color);
+                if has_save {
+                    draw_centered_text(strings.resume_saved, center - 60.0, 24.0, text_color);
+                }
+                draw_centered_text(strings.play, center + 40.0, 24.0, text_color);
+                let on_off = |on: bool| if on { strings.on } else { strings.off };
+                let walls = if config.wrap_walls {
+                    strings.walls_wrap
+                } else {
+                    strings.walls_solid
+                };
+                let mode_name = mode.name(strings);
+                let extents = config.field_extents();
+                let field_size = [extents.x, extents.y].map(|side| format!("{side:.1}"));
+                let goal = if config.timed {
+                    strings.goal_clock.to_string()
+                } else if config.goal {
+                    fill(strings.goal_grow, &[&TARGET_LENGTH])
+                } else {
+                    strings.goal_death.to_string()
+                };
+                let lines = [
+                    strings.option_line(strings.walls, &walls, "W"),
+                    strings.option_line(strings.mode, &mode_name, strings.mode_keys),
+                    strings.option_line(strings.music, &on_off(!music.muted), "M"),
+                    fill(strings.field, &[&field_size[0], &field_size[1]]),
+                    strings.option_line(strings.theme, &config.theme().name(strings), "T"),
+                    strings.option_line(
+                        strings.starting_length,
+                        &(config.starting_length + 1),
+                        "L",
+                    ),
+                    strings.option_line(strings.sudden_death, &on_off(config.sudden_death), "S"),
+                    strings.option_line(strings.hunger, &on_off(config.hunger), "H"),
+                    strings.option_line(strings.round_goal, &goal, "C"),
+                    strings.option_line(strings.difficulty, &config.difficulty.name(strings), "D"),
+                    strings.watch_saved_replay.to_string(),
+                    strings.open_settings.to_string(),
+                ];
+                for (index, line) in lines.iter().enumerate() {
+                    let y = center + 70.0 + index as f32 * 30.0;
+                    draw_centered_text(line, y, 24.0, text_color);
+                }
+            }
+            GameState::Settings { selected } => {
+                let center = screen_height() / 2.0;
+                draw_centered_text(strings.settings_title, center - 80.0, 48.0, text_color);
+                let on_off = |on: bool| if on { strings.on } else { strings.off };
+                let movement = if config.classic {
+                    strings.movement_classic
+                } else {
+                    strings.movement_smooth
+                };
+                let values = [
+                    Some(on_off(!music.muted)),
+                    Some(on_off(config.sound_effects)),
+                    Some(config.difficulty.name(strings)),
+                    Some(on_off(config.mouse_steering)),
+                    Some(on_off(config.direction_arrow)),
+                    Some(movement),
+                    Some(config.skin.name(strings)),
+                    Some(on_off(config.colorblind)),
+                    Some(on_off(config.fog)),
+                    Some(strings.language),
+                    None,
+                ];
+                for (index, (row, value)) in strings.settings.iter().zip(values).enumerate() {
+                    let text = match value {
+                        Some(value) => format!("{row}: {value}"),
+                        None => row.to_string(),
+                    };
+                    let y = center + index as f32 * 35.0;
+                    draw_menu_option(&text, y, index == selected, text_color);
+                }
+            }
+            GameState::Countdown { elapsed } => {
+                let step = (elapsed / COUNTDOWN_STEP) as usize;
+                let label = strings.countdown[step.min(strings.countdown.len() - 1)];
+                draw_centered_text(label, screen_height() / 2.0, 128.0, text_color);
+            }
+            GameState::Playing => {}
+            GameState::Replay { .. } => {
+                draw_centered_text(strings.replay_banner, 40.0, 32.0, text_color);
+            }
+            GameState::Paused { selected } => {
+                let center = screen_height() / 2.0;
+                draw_centered_text(strings.paused, center, 48.0, text_color);
+                for (index, option) in strings.pause_options.iter().enumerate() {
+                    let y = center + 50.0 + index as f32 * 35.0;
+                    draw_menu_option(option, y, index == selected, text_color);
+                }
+                if save_path.is_some() {
+                    draw_centered_text(strings.save_and_quit, center + 130.0, 24.0, text_color);
+                }
+            }
+            GameState::GameOver { score } => {
+                // Затемняем застывший кадр.
+                let dim = Color::new(0.0, 0.0, 0.0, 0.5);
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), dim);
+
+                let center = screen_height() / 2.0;
+                let game_over_text = if players.len() == 1 {
+                    fill(strings.game_over, &[&score])
+                } else {
+                    // Побеждает единственный оставшийся игрок, иначе ничья.
+                    // Если же время вышло и все живы, то побеждает набравший больше очков.
+                    let mut survivors = players.iter().enumerate().filter(|(_, p)| !p.lost());
+                    let winner = match (survivors.next(), survivors.next()) {
+                        (Some((index, _)), None) => Some(index),
+                        (Some(_), Some(_)) if players[0].score != players[1].score => {
+                            Some(if players[0].score > players[1].score {
+                                0
+                            } else {
+                                1
+                            })
+                        }
+                        _ => None,
+                    };
+                    match winner {
+                        Some(index) => fill(strings.player_wins, &[&(index + 1)]),
+                        None => strings.draw.to_string(),
+                    }
+                };
+                draw_centered_text(&game_over_text, center, 48.0, WHITE);
+
+                // Над результатом пишем, почему проиграл каждый из проигравших.
+                let deaths = players
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, p)| p.death.map(|cause| (index, cause)));
+                for (line, (index, cause)) in deaths.enumerate() {
+                    let text = if players.len() == 1 {
+                        cause.message(strings).to_string()
+                    } else {
+                        fill(
+                            strings.player_line,
+                            &[&(index + 1), &cause.message(strings)],
+                        )
+                    };
+                    let y = center - 60.0 - line as f32 * 30.0;
+                    draw_centered_text(&text, y, 28.0, WHITE);
+                }
+                // Ещё выше - итоги партии каждого игрока.
+                for (index, player) in players.iter().enumerate() {
+                    let stats = &player.stats;
+                    let summary = fill(
+                        strings.stats,
+                        &[
+                            &stats.fruits_eaten,
+                            &stats.max_length,
+                            &format_args!("{:.1}", stats.distance),
+                            &format_args!("{:.1}", stats.time),
+                        ],
+                    );
+                    let text = if players.len() == 1 {
+                        summary
+                    } else {
+                        fill(strings.player_line, &[&(index + 1), &summary])
+                    };
+                    let y = center - 150.0 + index as f32 * 26.0;
+                    draw_centered_text(&text, y, 24.0, WHITE);
+                }
+                draw_centered_text(strings.restart, center + 40.0, 24.0, WHITE);
+                draw_centered_text(strings.to_menu, center + 70.0, 24.0, WHITE);
+                if recording {
+                    draw_centered_text(strings.replay_options, center + 100.0, 24.0, WHITE);
+                }
+
+                // Таблица рекордов по местам.
+                if !leaderboard.entries.is_empty() {
+                    draw_centered_text(strings.top_scores, center + 150.0, 32.0, WHITE);
+                    for (rank, entry) in leaderboard.entries.iter().enumerate() {
+                        let y = center + 180.0 + rank as f32 * 26.0;
+                        let text = if entry.name.is_empty() {
+                            format!("{}. {}", rank + 1, entry.score)
+                        } else {
+                            format!("{}. {} - {}", rank + 1, entry.name, entry.score)
+                        };
+                        draw_centered_text(&text, y, 24.0, WHITE);
+                    }
+                }
+            }
+            GameState::NameEntry { score } => {
+                let dim = Color::new(0.0, 0.0, 0.0, 0.5);
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), dim);
+
+                let center = screen_height() / 2.0;
+                let record_text = fill(strings.new_high_score, &[&score]);
+                draw_centered_text(&record_text, center, 48.0, WHITE);
+                let name_text = fill(strings.name_prompt, &[&player_name]);
+                draw_centered_text(&name_text, center + 40.0, 32.0, WHITE);
+                draw_centered_text(strings.name_hint, center + 80.0, 24.0, WHITE);
+            }
+            GameState::Won { time } => {
+                let dim = Color::new(0.0, 0.0, 0.0, 0.5);
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), dim);
+
+                // Побеждает тот, чья змейка первой доросла до цели.
+                let center = screen_height() / 2.0;
+                let winner = players
+                    .iter()
+                    .position(|p| p.snake.length() >= TARGET_LENGTH);
+                let won_text = match winner {
+                    Some(index) if players.len() > 1 => fill(strings.player_wins, &[&(index + 1)]),
+                    _ => strings.you_win.to_string(),
+                };
+                draw_centered_text(&won_text, center, 48.0, WHITE);
+                let time = format_args!("{time:.1}");
+                let time_text = fill(strings.grew_to, &[&TARGET_LENGTH, &time]);
+                draw_centered_text(&time_text, center + 40.0, 32.0, WHITE);
+                draw_centered_text(strings.restart, center + 80.0, 24.0, WHITE);
+                draw_centered_text(strings.to_menu, center + 110.0, 24.0, WHITE);
+                if recording {
+                    draw_centered_text(strings.replay_options, center + 140.0, 24.0, WHITE);
+                }
+            }
+        }
+
+        if let Some(fade) = &fade {
+            fade.draw();
+        }
+
+        // Дожидаемся следующего кадра.
+        // Это нужно, чтобы FPS был стабилен и равен 60.
+        next_frame().await
+    }
+}
+
+/// Меняем раунд при смене экрана.
+/// Новый раунд начинается со своего зерна, чтобы его можно было записать.
+fn apply_round_action(
+    action: RoundAction,
+    game: &mut Game,
+    replay: &mut Replay,
+    recording: &mut bool,
+) {
+    match action {
+        RoundAction::NewRound => {
+            let round_seed = rand::rand() as u64;
+            game.start_round(round_seed);
+            *replay = Replay::new(round_seed, &game.config, game.mode);
+            *recording = true;
+        }
+        RoundAction::WatchReplay => {
+            replay.apply_to(&mut game.config);
+            game.mode = replay.mode;
+            game.start_round(replay.seed);
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Надписи интерфейса на разных языках.
+//! Все строки, которые видит игрок, берутся из набора `Strings` выбранного языка.
+//! Новый язык - это один литерал `Strings`. Строки, которые ещё не переведены,
+//! берутся из английского набора через `..ENGLISH`.
+//! Встроенный шрифт знает только латиницу, поэтому языки с другой письменностью
+//! потребуют своего шрифта.
+
+use std::fmt::{Display, Write};
+
+/// Все надписи интерфейса на одном языке.
+/// В шаблонах на место каждого `{}` по порядку подставляется значение, см. `fill`.
+pub struct Strings {
+    /// Название языка на нём самом, для экрана настроек.
+    pub language: &'static str,
+
+    // Во время игры.
+    pub scores: &'static str,
+    /// Номер игрока и что-нибудь о нём: очки или причина проигрыша.
+    pub player_line: &'static str,
+    pub length: &'static str,
+    pub combo: &'static str,
+    pub best: &'static str,
+    pub time_left: &'static str,
+    pub goal: &'static str,
+    pub controls_reversed: &'static str,
+    pub level: &'static str,
+    pub fps: &'static str,
+    pub frame_step: &'static str,
+    pub countdown: [&'static str; 4],
+    pub replay_banner: &'static str,
+
+    // Главное меню. Подсказка к настройке собирается из названия клавиши и `to_change`.
+    pub title: &'static str,
+    pub resume_saved: &'static str,
+    pub play: &'static str,
+    pub to_change: &'static str,
+    pub on: &'static str,
+    pub off: &'static str,
+    pub walls: &'static str,
+    pub walls_wrap: &'static str,
+    pub walls_solid: &'static str,
+    pub mode: &'static str,
+    pub mode_keys: &'static str,
+    /// Названия режимов игры в порядке `PlayersMode::ALL`.
+    pub modes: [&'static str; 3],
+    pub music: &'static str,
+    pub field: &'static str,
+    pub theme: &'static str,
+    /// Названия цветовых тем по `Theme::id`: сначала `THEMES`, потом палитра для дальтоников.
+    pub themes: [&'static str; 4],
+    pub starting_length: &'static str,
+    pub sudden_death: &'static str,
+    pub hunger: &'static str,
+    pub round_goal: &'static str,
+    pub goal_clock: &'static str,
+    pub goal_grow: &'static str,
+    pub goal_death: &'static str,
+    pub difficulty: &'static str,
+    /// Названия уровней сложности в порядке `Difficulty::ALL`.
+    pub difficulties: [&'static str; 3],
+    pub watch_saved_replay: &'static str,
+    pub open_settings: &'static str,
+
+    // Настройки.
+    pub settings_title: &'static str,
+    pub settings: [&'static str; 11],
+    pub movement_classic: &'static str,
+    pub movement_smooth: &'static str,
+    /// Названия раскрасок в порядке `Skin::ALL`.
+    pub skins: [&'static str; 3],
+
+    // Пауза.
+    pub paused: &'static str,
+    pub pause_options: [&'static str; 2],
+    pub save_and_quit: &'static str,
+
+    // Конец раунда.
+    pub game_over: &'static str,
+    pub player_wins: &'static str,
+    pub draw: &'static str,
+    /// Причины проигрыша в порядке вариантов `DeathCause`.
+    pub deaths: [&'static str; 6],
+    pub stats: &'static str,
+    pub restart: &'static str,
+    pub to_menu: &'static str,
+    pub replay_options: &'static str,
+    pub top_scores: &'static str,
+    pub new_high_score: &'static str,
+    pub name_prompt: &'static str,
+    pub name_hint: &'static str,
+    pub you_win: &'static str,
+    pub grew_to: &'static str,
+}
+
+/// Английские надписи. Из них берутся все строки, которых нет в переводе.
+pub const ENGLISH: Strings = Strings {
+    language: "English",
+
+    scores: "Scores: {}",
+    player_line: "Player {}: {}",
+    length: "Length: {}",
+    combo: "Combo: x{}",
+    best: "Best: {}",
+    time_left: "Time: {}",
+    goal: "Goal: length {}",
+    controls_reversed: "CONTROLS REVERSED!",
+    level: "Level {}",
+    fps: "FPS: {}",
+    frame_step: "FRAME STEP (F3 to step): head ({}, {}), direction ({}, {})",
+    countdown: ["3", "2", "1", "GO"],
+    replay_banner: "REPLAY (Escape to stop)",
+
+    title: "SNAKE",
+    resume_saved: "Press Space to resume the saved game",
+    play: "Press Enter to play",
+    to_change: "{} to change",
+    on: "on",
+    off: "off",
+    walls: "Walls",
+    walls_wrap: "wrap around",
+    walls_solid: "solid",
+    mode: "Mode",
+    mode_keys: "1, 2 or 3",
+    modes: ["single player", "two players", "versus computer"],
+    music: "Music",
+    field: "Field: {} x {} (+ or - to resize, A to reshape)",
+    theme: "Theme",
+    themes: ["Classic", "Dark", "Neon", "Colorblind"],
+    starting_length: "Starting length",
+    sudden_death: "Sudden death",
+    hunger: "Hunger",
+    round_goal: "Game",
+    goal_clock: "against the clock",
+    goal_grow: "grow to {}",
+    goal_death: "until death",
+    difficulty: "Difficulty",
+    difficulties: ["easy", "normal", "hard"],
+    watch_saved_replay: "Press R to watch the saved replay",
+    open_settings: "Press O for settings",
+
+    settings_title: "SETTINGS",
+    settings: [
+        "Music",
+        "Sound effects",
+        "Difficulty",
+        "Mouse steering",
+        "Direction arrow",
+        "Movement",
+        "Skin",
+        "Colorblind palette",
+        "Fog of war",
+        "Language",
+        "Back",
+    ],
+    movement_classic: "classic",
+    movement_smooth: "smooth",
+    skins: ["solid", "striped", "spotted"],
+
+    paused: "PAUSED",
+    pause_options: ["Resume", "Quit to Menu"],
+    save_and_quit: "Press S to save and quit to menu",
+
+    game_over: "Game Over - Score: {}",
+    player_wins: "Player {} wins!",
+    draw: "Draw!",
+    deaths: [
+        "You hit the wall!",
+        "You bit yourself!",
+        "You hit an obstacle!",
+        "You stepped on a bomb!",
+        "You ran into another snake!",
+        "You starved!",
+    ],
+    stats: "Fruits: {}  Max length: {}  Distance: {} m  Time: {} s",
+    restart: "Press Enter to restart",
+    to_menu: "Press Escape for menu",
+    replay_options: "Press R to watch the replay, S to save it",
+    top_scores: "Top scores",
+    new_high_score: "New high score: {}",
+    name_prompt: "Name: {}_",
+    name_hint: "Type your name and press Enter",
+    you_win: "Congratulations, you win!",
+    grew_to: "Grew to {} in {} s",
+};
+
+/// Испанские надписи. Отладочные строки не переведены и остаются английскими.
+pub const SPANISH: Strings = Strings {
+    language: "Español",
+
+    scores: "Puntos: {}",
+    player_line: "Jugador {}: {}",
+    length: "Longitud: {}",
+    combo: "Combo: x{}",
+    best: "Récord: {}",
+    time_left: "Tiempo: {}",
+    goal: "Meta: longitud {}",
+    controls_reversed: "¡CONTROLES INVERTIDOS!",
+    level: "Nivel {}",
+    countdown: ["3", "2", "1", "¡YA!"],
+    replay_banner: "REPETICIÓN (Escape para parar)",
+
+    title: "SERPIENTE",
+    resume_saved: "Pulsa Espacio para continuar la partida guardada",
+    play: "Pulsa Enter para jugar",
+    to_change: "{} para cambiar",
+    on: "sí",
+    off: "no",
+    walls: "Paredes",
+    walls_wrap: "se atraviesan",
+    walls_solid: "sólidas",
+    mode: "Modo",
+    mode_keys: "1, 2 o 3",
+    modes: ["un jugador", "dos jugadores", "contra la máquina"],
+    music: "Música",
+    field: "Campo: {} x {} (+ o - para el tamaño, A para la forma)",
+    theme: "Tema",
+    themes: ["Clásico", "Oscuro", "Neón", "Para daltónicos"],
+    starting_length: "Longitud inicial",
+    sudden_death: "Muerte súbita",
+    hunger: "Hambre",
+    round_goal: "Partida",
+    goal_clock: "contra el reloj",
+    goal_grow: "crecer hasta {}",
+    goal_death: "hasta morir",
+    difficulty: "Dificultad",
+    difficulties: ["fácil", "normal", "difícil"],
+    watch_saved_replay: "Pulsa R para ver la repetición guardada",
+    open_settings: "Pulsa O para los ajustes",
+
+    settings_title: "AJUSTES",
+    settings: [
+        "Música",
+        "Efectos de sonido",
+        "Dificultad",
+        "Dirigir con el ratón",
+        "Flecha de dirección",
+        "Movimiento",
+        "Aspecto",
+        "Paleta para daltónicos",
+        "Niebla",
+        "Idioma",
+        "Volver",
+    ],
+    movement_classic: "clásico",
+    movement_smooth: "suave",
+    skins: ["liso", "a rayas", "a manchas"],
+
+    paused: "PAUSA",
+    pause_options: ["Continuar", "Salir al menú"],
+    save_and_quit: "Pulsa S para guardar y salir al menú",
+
+    game_over: "Fin de la partida - Puntos: {}",
+    player_wins: "¡Gana el jugador {}!",
+    draw: "¡Empate!",
+    deaths: [
+        "¡Chocaste con la pared!",
+        "¡Te mordiste!",
+        "¡Chocaste con un obstáculo!",
+        "¡Pisaste una bomba!",
+        "¡Chocaste con otra serpiente!",
+        "¡Te moriste de hambre!",
+    ],
+    stats: "Frutas: {}  Longitud máxima: {}  Distancia: {} m  Tiempo: {} s",
+    restart: "Pulsa Enter para volver a jugar",
+    to_menu: "Pulsa Escape para ir al menú",
+    replay_options: "Pulsa R para ver la repetición, S para guardarla",
+    top_scores: "Mejores puntuaciones",
+    new_high_score: "¡Nuevo récord: {}!",
+    name_prompt: "Nombre: {}_",
+    name_hint: "Escribe tu nombre y pulsa Enter",
+    you_win: "¡Enhorabuena, has ganado!",
+    grew_to: "Creciste hasta {} en {} s",
+    ..ENGLISH
+};
+
+/// Языки, между которыми можно выбирать в настройках.
+pub const LANGUAGES: [&Strings; 2] = [&ENGLISH, &SPANISH];
+
+/// Переменная окружения с языком системы, например `es_ES.UTF-8`.
+const LANG_ENV_VAR: &str = "LANG";
+
+/// Номер языка в `LANGUAGES`, с которого начинается игра: язык системы,
+/// если для него есть перевод, иначе английский.
+pub fn startup_language() -> usize {
+    let system = std::env::var(LANG_ENV_VAR).unwrap_or_default();
+    if system.starts_with("es") {
+        1
+    } else {
+        0
+    }
+}
+
+/// Подставляем `args` по порядку на места `{}` в шаблоне и дописываем результат в `buffer`.
+/// Лишние `{}` остаются как есть, а лишние значения отбрасываются.
+pub fn write_filled(buffer: &mut String, template: &str, args: &[&dyn Display]) {
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        buffer.push_str(first);
+    }
+    for part in parts {
+        match args.next() {
+            Some(arg) => write!(buffer, "{arg}").expect("formatting into a String never fails"),
+            None => buffer.push_str("{}"),
+        }
+        buffer.push_str(part);
+    }
+}
+
+/// Шаблон с подставленными значениями.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut text = String::new();
+    write_filled(&mut text, template, args);
+    text
+}
+
+impl Strings {
+    /// Строка меню с настройкой: название, значение и клавиша, которой его поменять.
+    pub fn option_line(&self, label: &str, value: &dyn Display, keys: &str) -> String {
+        let hint = fill(self.to_change, &[&keys]);
+        format!("{label}: {value} ({hint})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::render::{COLORBLIND_THEME, THEMES};
+
+    #[test]
+    fn fill_puts_values_in_order() {
+        assert_eq!(
+            fill(ENGLISH.player_line, &[&2, &"You starved!"]),
+            "Player 2: You starved!"
+        );
+        assert_eq!(fill(SPANISH.player_wins, &[&1]), "¡Gana el jugador 1!");
+        assert_eq!(
+            fill(ENGLISH.grew_to, &[&30, &format_args!("{:.1}", 12.345)]),
+            "Grew to 30 in 12.3 s"
+        );
+        // Не хватило значений: место остаётся видно, а не пропадает молча.
+        assert_eq!(fill("{} and {}", &[&1]), "1 and {}");
+    }
+
+    #[test]
+    fn translation_keeps_placeholders_and_falls_back_to_english() {
+        let count = |text: &str| text.matches("{}").count();
+        for strings in LANGUAGES {
+            assert_eq!(count(strings.stats), count(ENGLISH.stats));
+            assert_eq!(count(strings.field), count(ENGLISH.field));
+            assert_eq!(count(strings.player_line), 2);
+            assert_eq!(count(strings.to_change), 1);
+        }
+        // Непереведённая строка берётся из английского набора.
+        assert_eq!(SPANISH.frame_step, ENGLISH.frame_step);
+        assert_ne!(SPANISH.play, ENGLISH.play);
+
+        // Название есть у каждой цветовой темы.
+        for theme in THEMES.iter().chain([&COLORBLIND_THEME]) {
+            assert_ne!(SPANISH.themes[theme.id], ENGLISH.themes[theme.id]);
+        }
+    }
+}
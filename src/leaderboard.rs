@@ -0,0 +1,167 @@
+//! Таблица лучших результатов.
+
+use std::fs;
+
+use crate::storage::data_file_path;
+
+/// Файл с таблицей рекордов рядом с исполняемым файлом игры.
+const LEADERBOARD_FILE_NAME: &str = "leaderboard.txt";
+
+/// Файл, в котором раньше хранился только лучший результат.
+const HIGH_SCORE_FILE_NAME: &str = "highscore.txt";
+
+/// Сколько лучших результатов хранится в таблице.
+pub const LEADERBOARD_SIZE: usize = 5;
+
+/// Сколько символов можно ввести в имени игрока.
+pub const MAX_NAME_LENGTH: usize = 8;
+
+/// Результат и имя того, кто его набрал.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+}
+
+/// Лучшие результаты, от большего к меньшему.
+#[derive(Debug, Default, PartialEq)]
+pub struct Leaderboard {
+    pub entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    /// Лучший результат или ноль, если таблица пуста.
+    pub fn best(&self) -> u32 {
+        self.entries.first().map_or(0, |entry| entry.score)
+    }
+
+    /// Место, на которое встанет результат, если он попадает в таблицу.
+    /// Нулевой результат в таблицу не попадает.
+    /// При равенстве очков новый результат встаёт после старых.
+    pub fn rank(&self, score: u32) -> Option<usize> {
+        let rank = self.entries.partition_point(|entry| entry.score >= score);
+        (score > 0 && rank < LEADERBOARD_SIZE).then_some(rank)
+    }
+
+    /// Вставляем результат на его место, оставляя только лучшие.
+    /// Возвращаем, попал ли результат в таблицу.
+    pub fn insert(&mut self, entry: Entry) -> bool {
+        let Some(rank) = self.rank(entry.score) else {
+            return false;
+        };
+        self.entries.insert(rank, entry);
+        self.entries.truncate(LEADERBOARD_SIZE);
+        true
+    }
+
+    /// Таблица в текстовом виде: по результату в строке, сначала очки, потом имя.
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{} {}\n", entry.score, entry.name))
+            .collect()
+    }
+
+    /// Разбираем таблицу, сохранённую `to_text`. Строка из одних очков - результат без имени.
+    /// Если хоть одна строка испорчена, то начинаем с пустой таблицы.
+    pub fn from_text(text: &str) -> Self {
+        let entries: Option<Vec<Entry>> = text
+            .lines()
+            .map(|line| {
+                let (score, name) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+                Some(Entry {
+                    name: name.trim().to_string(),
+                    score: score.parse().ok()?,
+                })
+            })
+            .collect();
+        let mut leaderboard = Self::default();
+        for entry in entries.unwrap_or_default() {
+            leaderboard.insert(entry);
+        }
+        leaderboard
+    }
+
+    /// Читаем таблицу из файла. Если таблицы ещё нет, то начинаем её с рекорда,
+    /// сохранённого предыдущими версиями игры.
+    pub fn load() -> Self {
+        let read = |name| data_file_path(name).and_then(|path| fs::read_to_string(path).ok());
+        match read(LEADERBOARD_FILE_NAME).or_else(|| read(HIGH_SCORE_FILE_NAME)) {
+            Some(text) => Self::from_text(&text),
+            None => Self::default(),
+        }
+    }
+
+    /// Сохраняем таблицу в файл.
+    /// Ошибку записи только сообщаем: из-за неё игра не должна падать.
+    pub fn save(&self) {
+        let Some(path) = data_file_path(LEADERBOARD_FILE_NAME) else {
+            return;
+        };
+        if let Err(e) = fs::write(&path, self.to_text()) {
+            eprintln!("Failed to save leaderboard to {}: {e}", path.display());
+        }
+    }
+}
+
+/// Дописываем к имени набранные символы. В имени только буквы и цифры,
+/// чтобы его было легко хранить в файле, и не длиннее `MAX_NAME_LENGTH`.
+pub fn type_name(name: &mut String, typed: impl IntoIterator<Item = char>) {
+    for c in typed {
+        if c.is_alphanumeric() && name.chars().count() < MAX_NAME_LENGTH {
+            name.push(c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> Entry {
+        Entry {
+            name: name.to_string(),
+            score,
+        }
+    }
+
+    #[test]
+    fn keeps_best_scores_in_order() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(leaderboard.best(), 0);
+        for score in [5, 20, 1, 7, 3, 12] {
+            leaderboard.insert(entry("A", score));
+        }
+        let scores: Vec<_> = leaderboard.entries.iter().map(|e| e.score).collect();
+        assert_eq!(scores, vec![20, 12, 7, 5, 3]);
+        assert_eq!(leaderboard.rank(2), None);
+        assert_eq!(leaderboard.rank(0), None);
+        assert!(leaderboard.insert(entry("B", 7)));
+        assert_eq!(leaderboard.entries[2], entry("A", 7));
+        assert_eq!(leaderboard.entries[3], entry("B", 7));
+        assert_eq!(leaderboard.best(), 20);
+    }
+
+    #[test]
+    fn text_round_trip_and_broken_text() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.insert(entry("Ann", 9));
+        leaderboard.insert(entry("", 4));
+        assert_eq!(Leaderboard::from_text(&leaderboard.to_text()), leaderboard);
+
+        // Рекорд из старого файла с одним числом тоже читается.
+        assert_eq!(Leaderboard::from_text("42").entries, vec![entry("", 42)]);
+        assert_eq!(Leaderboard::from_text(""), Leaderboard::default());
+        let broken = Leaderboard::from_text("5 Ann\nbroken\n");
+        assert_eq!(broken, Leaderboard::default());
+    }
+
+    #[test]
+    fn name_accepts_only_letters_and_digits() {
+        let mut name = String::new();
+        type_name(&mut name, "Ann 1\r\u{8}".chars());
+        assert_eq!(name, "Ann1");
+        type_name(&mut name, "abcdefgh".chars());
+        assert_eq!(name.len(), MAX_NAME_LENGTH);
+    }
+}
@@ -0,0 +1,144 @@
+//! Препятствия на поле.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::random::{rand_f32, random_position};
+use crate::render::ScreenTransform;
+use crate::save;
+
+/// Сколько препятствий расставляем на поле.
+pub const OBSTACLES_COUNT: usize = 4;
+
+/// Наименьший и наибольший радиус препятствия.
+pub const OBSTACLE_RADIUS_RANGE: (f32, f32) = (0.06, 0.12);
+
+/// Ближе этого расстояния к стартовой позиции змейки препятствия не ставим.
+pub const OBSTACLE_SAFE_DISTANCE: f32 = 0.5;
+
+/// Каждые столько очков начинается новый уровень с другой расстановкой препятствий.
+pub const LEVEL_SCORE_STEP: u32 = 10;
+
+/// Радиус препятствий в узорах уровней.
+pub const LEVEL_OBSTACLE_RADIUS: f32 = 0.08;
+
+/// Больше стольких препятствий в узоре не ставим, чтобы между ними оставались проходы.
+pub const LEVEL_MAX_OBSTACLES: usize = 14;
+
+/// Круглое препятствие внутри поля. Касание его головой завершает игру.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Obstacle {
+    #[serde(with = "save::vec2")]
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+impl Obstacle {
+    /// Препятствие появляется в случайном месте поля, но не рядом со стартом змейки.
+    pub fn respawn(config: &GameConfig) -> Self {
+        let (min_radius, max_radius) = OBSTACLE_RADIUS_RANGE;
+        let radius = min_radius + rand_f32() * (max_radius - min_radius);
+        loop {
+            let position = random_position(config);
+            if position.length() > OBSTACLE_SAFE_DISTANCE + radius {
+                return Self { position, radius };
+            }
+        }
+    }
+
+    /// Препятствие отображается в виде круга цвета темы.
+    pub fn draw(&self, config: &GameConfig, screen: &ScreenTransform) {
+        let radius_pixels = self.radius * screen.pixels_per_meter;
+        let screen_pos = screen.to_screen(self.position);
+        draw_circle(
+            screen_pos.x,
+            screen_pos.y,
+            radius_pixels,
+            config.theme().obstacle,
+        );
+    }
+
+    /// Проверка пересечения препятствия с заданым кругом.
+    pub fn intersect(&self, position: Vec2, radius: f32) -> bool {
+        self.position.distance(position) < radius + self.radius
+    }
+}
+
+/// Расставляем по полю новый набор препятствий.
+pub fn spawn_obstacles(config: &GameConfig) -> Vec<Obstacle> {
+    (0..OBSTACLES_COUNT)
+        .map(|_| Obstacle::respawn(config))
+        .collect()
+}
+
+/// Уровень, до которого дошёл игрок с таким счётом. Игра начинается с нулевого уровня.
+pub fn level_for_score(score: u32) -> u32 {
+    score / LEVEL_SCORE_STEP
+}
+
+/// Расстановка препятствий для уровня. На нулевом уровне препятствия расставлены случайно,
+/// а дальше по очереди идут узоры: кольцо, две стены и решётка.
+/// С каждым уровнем препятствий становится больше. Центр поля, откуда стартуют змейки,
+/// всегда остаётся свободным.
+pub fn obstacles_for_level(level: u32, config: &GameConfig) -> Vec<Obstacle> {
+    if level == 0 {
+        return spawn_obstacles(config);
+    }
+    let half = config.half_size();
+    let count = (OBSTACLES_COUNT + 2 * level as usize).min(LEVEL_MAX_OBSTACLES);
+    // Равномерно расставленные точки от `-extent` до `extent`.
+    let spread = |n: usize, extent: f32| {
+        (0..n).map(move |i| -extent + 2.0 * extent * i as f32 / (n - 1).max(1) as f32)
+    };
+    let positions: Vec<Vec2> = match (level - 1) % 3 {
+        0 => (0..count)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / count as f32;
+                Vec2::from_angle(angle) * 0.65 * half
+            })
+            .collect(),
+        1 => [-0.45, 0.45]
+            .into_iter()
+            .flat_map(|y| spread(count / 2, 0.6 * half).map(move |x| Vec2::new(x, y * half)))
+            .collect(),
+        _ => {
+            let side = ((count + 1) as f32).sqrt().ceil() as usize;
+            spread(side, 0.6 * half)
+                .flat_map(|y| spread(side, 0.6 * half).map(move |x| Vec2::new(x, y)))
+                .filter(|position| position.length() > OBSTACLE_SAFE_DISTANCE * half)
+                .take(count)
+                .collect()
+        }
+    };
+    positions
+        .into_iter()
+        .map(|position| Obstacle {
+            position,
+            radius: LEVEL_OBSTACLE_RADIUS,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_layouts_grow_and_keep_center_free() {
+        let config = GameConfig::default();
+        assert_eq!(level_for_score(LEVEL_SCORE_STEP - 1), 0);
+        assert_eq!(level_for_score(3 * LEVEL_SCORE_STEP), 3);
+
+        assert!(obstacles_for_level(2, &config).len() > obstacles_for_level(1, &config).len());
+        for level in 1..=6 {
+            let obstacles = obstacles_for_level(level, &config);
+            assert!(!obstacles.is_empty() && obstacles.len() <= LEVEL_MAX_OBSTACLES);
+            for obstacle in &obstacles {
+                assert!(!obstacle.intersect(Vec2::ZERO, 0.3));
+                let limit = config.half_size() - obstacle.radius;
+                assert!(obstacle.position.x.abs() <= limit && obstacle.position.y.abs() <= limit);
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Площадки на полу, которые ненадолго ускоряют или замедляют проезжающую змейку.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::obstacle::Obstacle;
+use crate::random::random_position;
+use crate::render::ScreenTransform;
+use crate::save;
+
+/// Радиус площадки.
+pub const SPEED_PAD_RADIUS: f32 = 0.12;
+
+/// Во сколько раз ускоряет зелёная площадка.
+pub const FAST_PAD_FACTOR: f32 = 1.5;
+
+/// Во сколько раз замедляет синяя площадка.
+pub const SLOW_PAD_FACTOR: f32 = 0.6;
+
+/// Сколько секунд действует площадка после того, как голова с неё съехала.
+pub const SPEED_PAD_DURATION: f32 = 1.5;
+
+/// Ближе этого расстояния к центру поля, откуда стартуют змейки, площадки не кладём.
+pub const SPEED_PAD_SAFE_DISTANCE: f32 = 0.3;
+
+/// Сколько случайных мест пробуем для площадки, прежде чем обойтись без неё.
+const SPEED_PAD_SPAWN_ATTEMPTS: usize = 50;
+
+/// Толщина обводки площадки в пикселях.
+const SPEED_PAD_RING_THICKNESS: f32 = 2.0;
+
+/// Площадка на полу. Она не исчезает, когда по ней проезжают, и срабатывает снова и снова.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeedPad {
+    #[serde(with = "save::vec2")]
+    pub position: Vec2,
+    pub radius: f32,
+    /// Во сколько раз меняется скорость змейки.
+    pub factor: f32,
+}
+
+/// Действие площадки на змейку, которая по ней проехала.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PadBoost {
+    pub factor: f32,
+    /// Сколько секунд действие ещё продлится.
+    pub time_left: f32,
+}
+
+impl SpeedPad {
+    /// Площадка с заданным множителем скорости в случайном месте поля:
+    /// не на препятствиях и не рядом со стартом змеек. Если места не нашлось, то её нет.
+    pub fn spawn(config: &GameConfig, obstacles: &[Obstacle], factor: f32) -> Option<Self> {
+        let is_free = |position: Vec2| {
+            position.length() > SPEED_PAD_SAFE_DISTANCE
+                && !obstacles
+                    .iter()
+                    .any(|obstacle| obstacle.intersect(position, SPEED_PAD_RADIUS))
+        };
+        (0..SPEED_PAD_SPAWN_ATTEMPTS)
+            .map(|_| random_position(config) * 0.8)
+            .find(|&position| is_free(position))
+            .map(|position| Self {
+                position,
+                radius: SPEED_PAD_RADIUS,
+                factor,
+            })
+    }
+
+    /// Стоит ли на площадке точка, например центр головы.
+    pub fn contains(&self, position: Vec2) -> bool {
+        self.position.distance(position) < self.radius
+    }
+
+    /// Площадка - полупрозрачный круг с обводкой: зелёный ускоряет, синий замедляет.
+    pub fn draw(&self, config: &GameConfig, screen: &ScreenTransform) {
+        let theme = config.theme();
+        let color = if self.factor > 1.0 {
+            theme.fast_pad
+        } else {
+            theme.slow_pad
+        };
+        let center = screen.to_screen(self.position);
+        let radius = self.radius * screen.pixels_per_meter;
+        draw_circle(center.x, center.y, radius, Color { a: 0.5, ..color });
+        draw_circle_lines(center.x, center.y, radius, SPEED_PAD_RING_THICKNESS, color);
+    }
+}
+
+/// Раскладываем по полю ускоряющую и замедляющую площадки.
+pub fn spawn_speed_pads(config: &GameConfig, obstacles: &[Obstacle]) -> Vec<SpeedPad> {
+    [FAST_PAD_FACTOR, SLOW_PAD_FACTOR]
+        .into_iter()
+        .filter_map(|factor| SpeedPad::spawn(config, obstacles, factor))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::obstacle::spawn_obstacles;
+    use crate::random::RNG_LOCK;
+
+    #[test]
+    fn pads_avoid_start_and_obstacles() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        rand::srand(4);
+        let config = GameConfig::default();
+        let obstacles = spawn_obstacles(&config);
+        for _ in 0..50 {
+            let pads = spawn_speed_pads(&config, &obstacles);
+            assert!(pads.iter().any(|pad| pad.factor > 1.0));
+            for pad in &pads {
+                assert!(!pad.contains(Vec2::ZERO));
+                assert!(!obstacles
+                    .iter()
+                    .any(|obstacle| obstacle.intersect(pad.position, pad.radius)));
+            }
+        }
+    }
+}
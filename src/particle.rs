@@ -0,0 +1,128 @@
+//! Частицы и всплывающие очки над съеденными фруктами.
+
+use macroquad::prelude::*;
+
+use crate::random::rand_f32;
+use crate::render::ScreenTransform;
+
+/// Сколько частиц разлетается от съеденного фрукта.
+pub const PARTICLES_PER_BURST: usize = 12;
+
+/// Сколько секунд живёт частица.
+pub const PARTICLE_LIFETIME: f32 = 0.5;
+
+/// Наибольшая скорость частицы.
+pub const PARTICLE_MAX_SPEED: f32 = 0.6;
+
+/// Начальный радиус частицы. Со временем частица уменьшается.
+pub const PARTICLE_RADIUS: f32 = 0.015;
+
+/// Частица, разлетающаяся от съеденного фрукта.
+pub struct Particle {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    /// Сколько секунд частица ещё проживёт.
+    pub life: f32,
+    pub color: Color,
+}
+
+impl Particle {
+    /// Перемещаем частицу и уменьшаем оставшееся ей время жизни.
+    pub fn update(&mut self, dt: f32) {
+        self.pos += self.vel * dt;
+        self.life -= dt;
+    }
+
+    /// Жива ли ещё частица.
+    pub fn is_alive(&self) -> bool {
+        self.life > 0.0
+    }
+
+    /// Частица отображается в виде круга, который уменьшается к концу жизни.
+    pub fn draw(&self, screen: &ScreenTransform) {
+        let radius = PARTICLE_RADIUS * self.life / PARTICLE_LIFETIME;
+        let radius_pixels = radius * screen.pixels_per_meter;
+        let screen_pos = screen.to_screen(self.pos);
+        draw_circle(screen_pos.x, screen_pos.y, radius_pixels, self.color);
+    }
+}
+
+/// Разбрасываем частицы во все стороны от заданной точки.
+pub fn spawn_burst(particles: &mut Vec<Particle>, pos: Vec2, color: Color) {
+    particles.extend((0..PARTICLES_PER_BURST).map(|_| {
+        let direction = Vec2::from_angle(rand_f32() * std::f32::consts::TAU);
+        Particle {
+            pos,
+            vel: direction * rand_f32() * PARTICLE_MAX_SPEED,
+            life: PARTICLE_LIFETIME,
+            color,
+        }
+    }));
+}
+
+/// Сколько секунд всплывают очки за съеденный фрукт.
+pub const FLOATING_TEXT_LIFETIME: f32 = 0.8;
+
+/// С какой скоростью очки всплывают вверх.
+pub const FLOATING_TEXT_SPEED: f32 = 0.25;
+
+/// Размер шрифта всплывающих очков.
+const FLOATING_TEXT_SIZE: f32 = 28.0;
+
+/// Очки, которые всплывают над съеденным фруктом и постепенно исчезают.
+pub struct FloatingText {
+    pub pos: Vec2,
+    pub value: u32,
+    /// Сколько секунд надпись ещё проживёт.
+    pub life: f32,
+}
+
+impl FloatingText {
+    /// Надпись с очками появляется там, где съели фрукт.
+    pub fn new(pos: Vec2, value: u32) -> Self {
+        Self {
+            pos,
+            value,
+            life: FLOATING_TEXT_LIFETIME,
+        }
+    }
+
+    /// Надпись поднимается вверх, а её время жизни уменьшается.
+    pub fn update(&mut self, dt: f32) {
+        self.pos.y += FLOATING_TEXT_SPEED * dt;
+        self.life -= dt;
+    }
+
+    /// Жива ли ещё надпись.
+    pub fn is_alive(&self) -> bool {
+        self.life > 0.0
+    }
+
+    /// Надпись бледнеет к концу жизни.
+    pub fn draw(&self, screen: &ScreenTransform) {
+        let text = format!("+{}", self.value);
+        let size = measure_text(&text, None, FLOATING_TEXT_SIZE as u16, 1.0);
+        let screen_pos = screen.to_screen(self.pos);
+        let color = Color {
+            a: (self.life / FLOATING_TEXT_LIFETIME).clamp(0.0, 1.0),
+            ..WHITE
+        };
+        let x = screen_pos.x - size.width / 2.0;
+        draw_text(&text, x, screen_pos.y, FLOATING_TEXT_SIZE, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floating_text_rises_and_expires() {
+        let mut text = FloatingText::new(Vec2::ZERO, 5);
+        text.update(FLOATING_TEXT_LIFETIME / 2.0);
+        assert!(text.is_alive());
+        assert!(text.pos.y > 0.0);
+        text.update(FLOATING_TEXT_LIFETIME);
+        assert!(!text.is_alive());
+    }
+}
@@ -0,0 +1,670 @@
+//! Игроки и управление змейками: клавиатура, геймпад или компьютер.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, GameConfig};
+use crate::fruit::Fruit;
+use crate::lang::Strings;
+use crate::pad::{PadBoost, SpeedPad, SPEED_PAD_DURATION};
+use crate::power_up::{ActiveEffect, PowerUpKind};
+use crate::snake::{DeathCause, Snake};
+
+/// Клавиши управления змейкой.
+pub struct KeyBindings {
+    /// Поворот налево.
+    pub left: KeyCode,
+    /// Дополнительная клавиша поворота налево.
+    pub left_alt: KeyCode,
+    /// Поворот направо.
+    pub right: KeyCode,
+    /// Дополнительная клавиша поворота направо.
+    pub right_alt: KeyCode,
+    /// Ускорение.
+    pub boost: KeyCode,
+    /// Пауза. Escape ставит игру на паузу всегда, независимо от этой настройки.
+    pub pause: KeyCode,
+}
+
+impl KeyBindings {
+    /// Зажата ли любая из клавиш поворота налево.
+    pub fn is_left_down(&self) -> bool {
+        is_key_down(self.left) || is_key_down(self.left_alt)
+    }
+
+    /// Зажата ли любая из клавиш поворота направо.
+    pub fn is_right_down(&self) -> bool {
+        is_key_down(self.right) || is_key_down(self.right_alt)
+    }
+
+    /// Зажата ли клавиша ускорения.
+    pub fn is_boost_down(&self) -> bool {
+        is_key_down(self.boost)
+    }
+}
+
+impl KeyBindings {
+    /// Клавиши второго игрока: запятая и точка или стрелки цифрового блока,
+    /// ускорение на правом Shift.
+    pub fn second_player() -> Self {
+        Self {
+            left: KeyCode::Comma,
+            left_alt: KeyCode::Kp4,
+            right: KeyCode::Period,
+            right_alt: KeyCode::Kp6,
+            boost: KeyCode::RightShift,
+            pause: KeyCode::P,
+        }
+    }
+}
+
+/// По умолчанию управляем стрелками или A и D, ускоряемся на левом Shift, а пауза на P.
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: KeyCode::Left,
+            left_alt: KeyCode::A,
+            right: KeyCode::Right,
+            right_alt: KeyCode::D,
+            boost: KeyCode::LeftShift,
+            pause: KeyCode::P,
+        }
+    }
+}
+
+/// Отклонение стика, меньше которого считаем, что стик не тронут.
+#[cfg(feature = "gamepad")]
+pub const GAMEPAD_DEADZONE: f32 = 0.15;
+
+/// Управление с геймпада.
+/// В macroquad пока нет поддержки геймпадов, поэтому используем gilrs.
+/// Без фичи `gamepad` геймпад просто не опрашивается.
+pub struct Gamepad {
+    #[cfg(feature = "gamepad")]
+    pub gilrs: Option<gilrs::Gilrs>,
+}
+
+impl Gamepad {
+    /// Подключаемся к геймпадам. Если не получилось, играем без них.
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "gamepad")]
+            gilrs: gilrs::Gilrs::new().ok(),
+        }
+    }
+
+    /// Отклонение левого стика по горизонтали (ось `Axis::LeftStickX`), от -1.0 (влево) до 1.0 (вправо).
+    /// Внутри зоны нечувствительности возвращаем 0.0, а за ней плавно растим значение от нуля.
+    #[cfg(feature = "gamepad")]
+    pub fn steering(&mut self) -> f32 {
+        let Some(gilrs) = &mut self.gilrs else {
+            return 0.0;
+        };
+
+        // Чтобы gilrs обновил состояние стиков, нужно вычитать все события.
+        while gilrs.next_event().is_some() {}
+
+        let value = gilrs
+            .gamepads()
+            .map(|(_, gamepad)| gamepad.value(gilrs::Axis::LeftStickX))
+            .find(|value| value.abs() > GAMEPAD_DEADZONE)
+            .unwrap_or(0.0);
+
+        if value.abs() <= GAMEPAD_DEADZONE {
+            return 0.0;
+        }
+        value.signum() * (value.abs() - GAMEPAD_DEADZONE) / (1.0 - GAMEPAD_DEADZONE)
+    }
+
+    /// Без поддержки геймпада стик всегда в покое.
+    #[cfg(not(feature = "gamepad"))]
+    pub fn steering(&mut self) -> f32 {
+        0.0
+    }
+}
+
+/// За сколько секунд нужно съесть следующий фрукт, чтобы увеличить множитель очков.
+pub const COMBO_WINDOW: f32 = 2.5;
+
+/// Через сколько секунд без еды голодная змейка теряет сегмент.
+pub const HUNGER_INTERVAL: f32 = 8.0;
+
+/// Кто управляет змейкой.
+pub enum Control {
+    /// Человек с клавиатуры.
+    Keyboard(KeyBindings),
+    /// Компьютер.
+    Ai,
+}
+
+/// Во сколько раз быстрее змейка во время ускорения.
+pub const BOOST_FACTOR: f32 = 1.6;
+
+/// Какую часть сил ускорение тратит за секунду.
+pub const BOOST_DRAIN_PER_SEC: f32 = 0.5;
+
+/// Какую часть сил змейка восстанавливает за секунду без ускорения.
+pub const BOOST_REGEN_PER_SEC: f32 = 0.2;
+
+/// Сколько сил нужно восстановить после полного истощения, чтобы снова ускориться.
+pub const BOOST_REUSE_STAMINA: f32 = 0.3;
+
+/// Итоги партии игрока, которые показываем после конца раунда.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Сколько фруктов съедено, включая бонусные.
+    pub fruits_eaten: u32,
+    /// Наибольшая длина змейки за раунд.
+    pub max_length: u32,
+    /// Сколько метров прошла голова.
+    pub distance: f32,
+    /// Сколько секунд змейка продержалась.
+    pub time: f32,
+}
+
+impl RunStats {
+    /// Учитываем шаг игры длиной `dt` секунд, который змейка провела на поле.
+    pub fn record(&mut self, snake: &Snake, dt: f32) {
+        let speed = snake.head.speed * snake.head.speed_multiplier;
+        self.distance += speed * dt;
+        self.time += dt;
+        self.max_length = self.max_length.max(snake.length());
+    }
+}
+
+/// Игрок: змейка, управление и всё, что относится к его партии.
+pub struct Player {
+    pub snake: Snake,
+    pub control: Control,
+    pub score: u32,
+    /// Множитель очков за фрукты, съеденные один за другим.
+    pub combo: u32,
+    /// Сколько секунд осталось, чтобы съесть следующий фрукт и увеличить множитель.
+    pub combo_time_left: f32,
+    /// Действующий эффект подобранного бонуса.
+    pub effect: Option<ActiveEffect>,
+    /// Запас сил на ускорение, от 0 до 1.
+    pub stamina: f32,
+    /// Ускоряется ли змейка прямо сейчас.
+    pub boosting: bool,
+    /// Силы кончились, и ускориться снова можно будет только после отдыха.
+    pub exhausted: bool,
+    /// Почему игрок проиграл в этом раунде, если проиграл.
+    pub death: Option<DeathCause>,
+    /// Сколько секунд змейка уже не ела. Нужно только в игре с голодом.
+    pub time_since_eaten: f32,
+    /// Действие площадки, по которой змейка недавно проехала.
+    pub pad_boost: Option<PadBoost>,
+    /// Итоги партии. С началом нового раунда они обнуляются.
+    pub stats: RunStats,
+}
+
+impl Player {
+    /// Новый игрок в заданной точке со змейкой стартовой длины из настроек.
+    pub fn new(control: Control, color: Color, position: Vec2, config: &GameConfig) -> Self {
+        let mut snake = Snake {
+            color,
+            ..Snake::with_length(config.starting_length, &config.tuning)
+        };
+        snake.move_to(position);
+        Self::starting(snake, control)
+    }
+
+    /// Возвращаем игрока на старт нового раунда. Змейка восстанавливается на месте
+    /// через `Snake::reset`, поэтому память под её сегменты не выделяется заново.
+    /// Остальное, что относится к партии, сбрасывается так же, как у нового игрока.
+    pub fn restart(&mut self, control: Control, color: Color, position: Vec2, config: &GameConfig) {
+        self.snake.reset(config.starting_length, &config.tuning);
+        self.snake.color = color;
+        self.snake.move_to(position);
+        self.control = control;
+        self.score = 0;
+        self.combo = 1;
+        self.combo_time_left = 0.0;
+        self.effect = None;
+        self.stamina = 1.0;
+        self.boosting = false;
+        self.exhausted = false;
+        self.death = None;
+        self.time_since_eaten = 0.0;
+        self.pad_boost = None;
+        self.stats = RunStats::default();
+    }
+
+    /// Игрок в начале раунда с уже поставленной на старт змейкой.
+    fn starting(snake: Snake, control: Control) -> Self {
+        Self {
+            snake,
+            control,
+            score: 0,
+            combo: 1,
+            combo_time_left: 0.0,
+            effect: None,
+            stamina: 1.0,
+            boosting: false,
+            exhausted: false,
+            death: None,
+            time_since_eaten: 0.0,
+            pad_boost: None,
+            stats: RunStats::default(),
+        }
+    }
+
+    /// Хочет ли игрок ускориться. Компьютер не ускоряется никогда.
+    pub fn wants_boost(&self) -> bool {
+        match &self.control {
+            Control::Keyboard(keys) => keys.is_boost_down(),
+            Control::Ai => false,
+        }
+    }
+
+    /// Пока ускорение зажато и силы есть, змейка быстрее, а силы тратятся.
+    /// Иначе силы восстанавливаются. Если силы кончились, то ускорение отключается,
+    /// пока они не восстановятся до `BOOST_REUSE_STAMINA`.
+    pub fn update_boost(&mut self, wants_boost: bool, dt: f32) {
+        if self.exhausted && self.stamina >= BOOST_REUSE_STAMINA {
+            self.exhausted = false;
+        }
+        self.boosting = wants_boost && !self.exhausted;
+        if self.boosting {
+            self.stamina -= BOOST_DRAIN_PER_SEC * dt;
+            if self.stamina <= 0.0 {
+                self.stamina = 0.0;
+                self.exhausted = true;
+            }
+        } else {
+            self.stamina = (self.stamina + BOOST_REGEN_PER_SEC * dt).min(1.0);
+        }
+        self.update_snake_effects();
+    }
+
+    /// Пока голова стоит на площадке, её действие начинается заново,
+    /// а съехав с площадки, змейка сохраняет новую скорость ещё `SPEED_PAD_DURATION` секунд.
+    /// Саму скорость змейки пересчитывает следующий за этим `update_boost`.
+    pub fn update_speed_pads(&mut self, pads: &[SpeedPad], dt: f32) {
+        let head = self.snake.head.position();
+        if let Some(pad) = pads.iter().find(|pad| pad.contains(head)) {
+            self.pad_boost = Some(PadBoost {
+                factor: pad.factor,
+                time_left: SPEED_PAD_DURATION,
+            });
+        } else if let Some(boost) = &mut self.pad_boost {
+            boost.time_left -= dt;
+            if boost.time_left <= 0.0 {
+                self.pad_boost = None;
+            }
+        }
+    }
+
+    /// Скорость змейки меняют действующий бонус, площадка и ускорение.
+    /// Неуязвимой змейка остаётся, пока действует бонус неуязвимости.
+    fn update_snake_effects(&mut self) {
+        let effect_factor = self
+            .effect
+            .as_ref()
+            .map_or(1.0, |active| active.kind.speed_factor());
+        let pad_factor = self.pad_boost.map_or(1.0, |boost| boost.factor);
+        let boost_factor = if self.boosting { BOOST_FACTOR } else { 1.0 };
+        self.snake.head.speed_multiplier = effect_factor * pad_factor * boost_factor;
+        self.snake.invincible = self.has_effect(PowerUpKind::Invincibility);
+    }
+
+    /// Скорость поворота змейки в зависимости от нажатых клавиш.
+    /// Если зажаты клавиши обоих направлений, то повороты взаимно гасятся.
+    /// Компьютер же поворачивает к ближайшему фрукту.
+    pub fn rotation(&self, fruits: &[Fruit], config: &GameConfig) -> f32 {
+        match &self.control {
+            Control::Keyboard(keys) => {
+                let mut rotation = 0.0;
+                if keys.is_left_down() {
+                    rotation += config.tuning.rotation_per_sec;
+                }
+                if keys.is_right_down() {
+                    rotation -= config.tuning.rotation_per_sec;
+                }
+                rotation
+            }
+            Control::Ai => {
+                let rotation = self
+                    .snake
+                    .nearest_fruit(fruits)
+                    .map_or(0.0, |fruit| self.snake.ai_rotation(fruit, config));
+                if config.classic {
+                    self.snake.grid_turn(rotation, config)
+                } else {
+                    rotation
+                }
+            }
+        }
+    }
+
+    /// Начисляем очки за съеденный фрукт с учётом множителя.
+    /// Если предыдущий фрукт был съеден недавно, то множитель растёт.
+    /// Голова змейки при этом ненадолго раздувается.
+    pub fn eat(&mut self, fruit: &Fruit, tuning: &Config) {
+        self.combo = if self.combo_time_left > 0.0 {
+            self.combo + 1
+        } else {
+            1
+        };
+        self.combo_time_left = COMBO_WINDOW;
+        self.score += fruit.value(tuning) * self.combo;
+        self.snake.head.bite();
+        self.time_since_eaten = 0.0;
+        self.stats.fruits_eaten += 1;
+    }
+
+    /// В игре с голодом змейка, которая не ела `HUNGER_INTERVAL` секунд, теряет сегмент,
+    /// и отсчёт начинается заново. Если терять уже нечего, то змейка умирает от голода.
+    pub fn update_hunger(&mut self, dt: f32, config: &GameConfig) -> Option<DeathCause> {
+        if !config.hunger {
+            return None;
+        }
+        self.time_since_eaten += dt;
+        if self.time_since_eaten < HUNGER_INTERVAL {
+            return None;
+        }
+        self.time_since_eaten = 0.0;
+        if self.snake.length() <= 1 {
+            return Some(DeathCause::Starved);
+        }
+        self.snake.remove_unit();
+        None
+    }
+
+    /// Насколько змейка сыта: 1 сразу после еды и 0, когда она вот-вот потеряет сегмент.
+    pub fn satiety(&self) -> f32 {
+        1.0 - self.time_since_eaten / HUNGER_INTERVAL
+    }
+
+    /// Отсчитываем время до сброса множителя очков.
+    pub fn update_combo(&mut self, dt: f32) {
+        self.combo_time_left -= dt;
+        if self.combo_time_left <= 0.0 {
+            self.combo_time_left = 0.0;
+            self.combo = 1;
+        }
+    }
+
+    /// Подобранный бонус начинает действовать сразу.
+    /// Эффекты не складываются: последний подобранный бонус заменяет предыдущий
+    /// и заново запускает отсчёт времени.
+    pub fn apply_power_up(&mut self, kind: PowerUpKind) {
+        self.effect = Some(ActiveEffect {
+            kind,
+            time_left: kind.duration(),
+        });
+        self.update_snake_effects();
+    }
+
+    /// Поворот, который на самом деле получает змейка.
+    /// Пока действует перевёрнутое управление, поворот меняет знак.
+    pub fn steer(&self, rotation: f32) -> f32 {
+        let factor = self
+            .effect
+            .as_ref()
+            .map_or(1.0, |active| active.kind.rotation_factor());
+        rotation * factor
+    }
+
+    /// Проиграл ли игрок в этом раунде.
+    pub fn lost(&self) -> bool {
+        self.death.is_some()
+    }
+
+    /// Действует ли сейчас бонус заданной разновидности.
+    pub fn has_effect(&self, kind: PowerUpKind) -> bool {
+        self.effect
+            .as_ref()
+            .is_some_and(|active| active.kind == kind)
+    }
+
+    /// Снимаем действующий эффект, например когда игрок проиграл.
+    pub fn clear_effect(&mut self) {
+        self.effect = None;
+        self.update_snake_effects();
+    }
+
+    /// Отсчитываем время действия эффекта.
+    /// Когда эффект заканчивается, возвращаем змейке обычную скорость.
+    pub fn update_effect(&mut self, dt: f32) {
+        if let Some(active) = &mut self.effect {
+            active.time_left -= dt;
+            if active.time_left <= 0.0 {
+                self.effect = None;
+                self.update_snake_effects();
+            }
+        }
+    }
+}
+
+/// С кем играет первый игрок.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayersMode {
+    /// Один игрок.
+    Single,
+    /// Двое игроков на одной клавиатуре.
+    TwoPlayers,
+    /// Игрок против компьютера.
+    VersusAi,
+}
+
+impl PlayersMode {
+    /// Все режимы в порядке объявления.
+    pub const ALL: [Self; 3] = [Self::Single, Self::TwoPlayers, Self::VersusAi];
+
+    /// Название режима для меню.
+    pub fn name(self, strings: &Strings) -> &'static str {
+        strings.modes[self as usize]
+    }
+
+    /// Сколько змеек в раунде. Управление записывается для каждой, включая компьютерную.
+    pub fn players_count(self) -> usize {
+        match self {
+            Self::Single => 1,
+            Self::TwoPlayers | Self::VersusAi => 2,
+        }
+    }
+}
+
+/// Создаём игроков для нового раунда.
+/// Одиночная змейка стартует из центра поля, а две змейки - параллельно друг другу.
+pub fn spawn_players(mode: PlayersMode, config: &GameConfig) -> Vec<Player> {
+    player_starts(mode, config)
+        .into_iter()
+        .map(|(control, color, position)| Player::new(control, color, position, config))
+        .collect()
+}
+
+/// Возвращаем игроков на старт нового раунда, не создавая их заново.
+/// Если с прошлого раунда сменилось число игроков, то создаём их как в `spawn_players`.
+pub fn restart_players(players: &mut Vec<Player>, mode: PlayersMode, config: &GameConfig) {
+    let starts = player_starts(mode, config);
+    if players.len() != starts.len() {
+        *players = spawn_players(mode, config);
+        return;
+    }
+    for (player, (control, color, position)) in players.iter_mut().zip(starts) {
+        player.restart(control, color, position, config);
+    }
+}
+
+/// Управление, цвет и стартовая точка каждого игрока в режиме `mode`.
+fn player_starts(mode: PlayersMode, config: &GameConfig) -> Vec<(Control, Color, Vec2)> {
+    let [first_color, second_color] = config.theme().snakes;
+    let first_control = Control::Keyboard(KeyBindings::default());
+    let second_control = match mode {
+        PlayersMode::Single => return vec![(first_control, first_color, Vec2::ZERO)],
+        PlayersMode::TwoPlayers => Control::Keyboard(KeyBindings::second_player()),
+        PlayersMode::VersusAi => Control::Ai,
+    };
+    vec![
+        (first_control, first_color, Vec2::new(0.0, 0.25)),
+        (second_control, second_color, Vec2::new(0.0, -0.25)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fruit::FRUIT_RADIUS;
+    use crate::game::FRAME_STEP_KEY;
+    use crate::power_up::{INVINCIBILITY_DURATION, REVERSE_CONTROLS_DURATION};
+
+    #[test]
+    fn frame_step_key_is_not_bound() {
+        for keys in [KeyBindings::default(), KeyBindings::second_player()] {
+            let bound = [
+                keys.left,
+                keys.left_alt,
+                keys.right,
+                keys.right_alt,
+                keys.boost,
+                keys.pause,
+            ];
+            assert!(!bound.contains(&FRAME_STEP_KEY));
+        }
+    }
+
+    #[test]
+    fn hungry_snake_shrinks_and_starves() {
+        let config = GameConfig {
+            hunger: true,
+            starting_length: 2,
+            ..GameConfig::default()
+        };
+        let mut player = Player::new(Control::Ai, WHITE, Vec2::ZERO, &config);
+        assert_eq!(player.snake.length(), 3);
+        assert_eq!(player.update_hunger(HUNGER_INTERVAL - 0.1, &config), None);
+        assert_eq!(player.update_hunger(0.1, &config), None);
+        assert_eq!(player.snake.length(), 2);
+
+        // Еда сбрасывает голод.
+        let fruit = Fruit::at(Vec2::ZERO, FRUIT_RADIUS);
+        player.update_hunger(HUNGER_INTERVAL / 2.0, &config);
+        player.eat(&fruit, &config.tuning);
+        assert_eq!(player.satiety(), 1.0);
+
+        assert_eq!(player.update_hunger(HUNGER_INTERVAL, &config), None);
+        assert_eq!(player.snake.length(), 1);
+        let starved = player.update_hunger(HUNGER_INTERVAL, &config);
+        assert_eq!(starved, Some(DeathCause::Starved));
+
+        // Без голода змейка может не есть сколько угодно.
+        let calm = GameConfig::default();
+        assert_eq!(player.update_hunger(HUNGER_INTERVAL * 10.0, &calm), None);
+    }
+
+    #[test]
+    fn speed_pad_lasts_after_leaving_and_retriggers() {
+        let config = GameConfig::default();
+        let mut player = Player::new(Control::Ai, WHITE, Vec2::ZERO, &config);
+        let pad = SpeedPad {
+            position: Vec2::ZERO,
+            radius: 0.1,
+            factor: 1.5,
+        };
+        player.update_speed_pads(&[pad], 0.1);
+        player.update_boost(false, 0.1);
+        assert_eq!(player.snake.head.speed_multiplier, 1.5);
+
+        // Съехав с площадки, змейка ещё немного едет быстрее.
+        player.snake.move_to(Vec2::new(0.5, 0.0));
+        player.update_speed_pads(&[pad], SPEED_PAD_DURATION - 0.1);
+        player.update_boost(false, 0.1);
+        assert_eq!(player.snake.head.speed_multiplier, 1.5);
+        player.update_speed_pads(&[pad], 0.2);
+        player.update_boost(false, 0.1);
+        assert_eq!(player.snake.head.speed_multiplier, 1.0);
+
+        // Площадка никуда не делась и срабатывает снова.
+        player.snake.move_to(Vec2::ZERO);
+        player.update_speed_pads(&[pad], 0.1);
+        assert!(player.pad_boost.is_some());
+    }
+
+    #[test]
+    fn quick_eating_grows_combo() {
+        let config = GameConfig::default();
+        let tuning = config.tuning;
+        let mut player = Player::new(
+            Control::Keyboard(KeyBindings::default()),
+            WHITE,
+            Vec2::ZERO,
+            &config,
+        );
+        let fruit = Fruit::at(Vec2::ZERO, FRUIT_RADIUS);
+
+        player.eat(&fruit, &tuning);
+        player.update_combo(1.0);
+        player.eat(&fruit, &tuning);
+        assert_eq!(player.combo, 2);
+        assert_eq!(player.score, 1 + 2);
+
+        // Если долго не есть, то множитель сбрасывается.
+        player.update_combo(COMBO_WINDOW);
+        assert_eq!(player.combo, 1);
+        player.eat(&fruit, &tuning);
+        assert_eq!(player.score, 1 + 2 + 1);
+    }
+
+    #[test]
+    fn reversed_controls_flip_rotation_until_replaced() {
+        let mut player = Player::new(Control::Ai, WHITE, Vec2::ZERO, &GameConfig::default());
+        assert_eq!(player.steer(2.0), 2.0);
+
+        player.apply_power_up(PowerUpKind::ReverseControls);
+        assert_eq!(player.steer(2.0), -2.0);
+        assert_eq!(player.snake.head.speed_multiplier, 1.0);
+
+        // Эффекты не складываются: ускорение возвращает обычное управление.
+        player.apply_power_up(PowerUpKind::SpeedBoost);
+        assert_eq!(player.steer(2.0), 2.0);
+
+        player.apply_power_up(PowerUpKind::ReverseControls);
+        player.update_effect(REVERSE_CONTROLS_DURATION);
+        assert_eq!(player.steer(2.0), 2.0);
+    }
+
+    #[test]
+    fn invincibility_ends_with_effect() {
+        let mut player = Player::new(Control::Ai, WHITE, Vec2::ZERO, &GameConfig::default());
+        player.apply_power_up(PowerUpKind::Invincibility);
+        assert!(player.snake.invincible);
+        player.update_effect(INVINCIBILITY_DURATION / 2.0);
+        assert!(player.snake.invincible);
+        player.update_effect(INVINCIBILITY_DURATION);
+        assert!(!player.snake.invincible);
+    }
+
+    #[test]
+    fn boost_drains_stamina_and_needs_recharge() {
+        let mut player = Player::new(
+            Control::Keyboard(KeyBindings::default()),
+            WHITE,
+            Vec2::ZERO,
+            &GameConfig::default(),
+        );
+        player.update_boost(true, 0.1);
+        assert!(player.boosting);
+        assert_eq!(player.snake.head.speed_multiplier, BOOST_FACTOR);
+
+        // Силы кончились, и ускорение отключилось само.
+        while player.stamina > 0.0 {
+            player.update_boost(true, 0.1);
+        }
+        player.update_boost(true, 0.1);
+        assert!(!player.boosting);
+        assert_eq!(player.snake.head.speed_multiplier, 1.0);
+
+        // Немного отдохнуть недостаточно, нужно восстановить часть сил.
+        player.update_boost(false, 0.1);
+        player.update_boost(true, 0.1);
+        assert!(!player.boosting);
+        let rest = BOOST_REUSE_STAMINA / BOOST_REGEN_PER_SEC;
+        player.update_boost(false, rest);
+        player.update_boost(true, 0.1);
+        assert!(player.boosting);
+    }
+}
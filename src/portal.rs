@@ -0,0 +1,210 @@
+//! Пара связанных порталов: голова, попавшая в один из них, выходит из другого.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::obstacle::Obstacle;
+use crate::random::random_position;
+use crate::render::ScreenTransform;
+use crate::save;
+use crate::snake::Snake;
+
+/// Радиус портала. Голова проходит в портал, когда её центр оказывается внутри него.
+pub const PORTAL_RADIUS: f32 = 0.06;
+
+/// Сколько секунд после перехода змейка не может снова войти в портал.
+/// Иначе голова, вышедшая из портала, сразу же вернулась бы обратно.
+pub const PORTAL_COOLDOWN: f32 = 0.5;
+
+/// Ближе этого расстояния к центру поля, откуда стартуют змейки, порталы не ставим.
+pub const PORTAL_SAFE_DISTANCE: f32 = 0.3;
+
+/// Порталы ставим не у самых стен, чтобы из них было куда выйти.
+const PORTAL_FIELD_SHARE: f32 = 0.8;
+
+/// Сколько случайных мест пробуем для каждого портала, прежде чем обойтись без него.
+const PORTAL_SPAWN_ATTEMPTS: usize = 50;
+
+/// Так далеко от центра закрытые порталы оказываются за стенами поля любого размера.
+const CLOSED_PORTAL_DISTANCE: f32 = 10.0;
+
+/// Толщина кольца портала в пикселях.
+const PORTAL_RING_THICKNESS: f32 = 3.0;
+
+/// Пара связанных порталов.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Portal {
+    #[serde(with = "save::vec2")]
+    pub a: Vec2,
+    #[serde(with = "save::vec2")]
+    pub b: Vec2,
+}
+
+impl Portal {
+    /// Порталы появляются в случайных местах поля: подальше друг от друга,
+    /// не на препятствиях и не рядом со стартом змеек. Если достаточно далёкой пары
+    /// не нашлось, то берём самую далёкую из найденных, а если места нет совсем,
+    /// то порталы закрыты.
+    pub fn respawn(config: &GameConfig, obstacles: &[Obstacle]) -> Self {
+        let is_free = |position: Vec2| {
+            position.length() > PORTAL_SAFE_DISTANCE
+                && !obstacles
+                    .iter()
+                    .any(|obstacle| obstacle.intersect(position, PORTAL_RADIUS))
+        };
+        let free_position = || {
+            (0..PORTAL_SPAWN_ATTEMPTS)
+                .map(|_| random_position(config) * PORTAL_FIELD_SHARE)
+                .find(|&position| is_free(position))
+        };
+        let Some(a) = free_position() else {
+            return Self::closed();
+        };
+        let mut farthest: Option<Vec2> = None;
+        for _ in 0..PORTAL_SPAWN_ATTEMPTS {
+            let Some(b) = free_position() else {
+                break;
+            };
+            if a.distance(b) > config.half_size() {
+                return Self { a, b };
+            }
+            if farthest.is_none_or(|farthest| a.distance(b) > a.distance(farthest)) {
+                farthest = Some(b);
+            }
+        }
+        farthest.map_or(Self::closed(), |b| Self { a, b })
+    }
+
+    /// Порталы за стенами поля. Они всегда закрыты, см. `is_open`.
+    pub fn closed() -> Self {
+        Self {
+            a: Vec2::splat(CLOSED_PORTAL_DISTANCE),
+            b: Vec2::splat(-CLOSED_PORTAL_DISTANCE),
+        }
+    }
+
+    /// Оба портала внутри поля. В режиме внезапной смерти стены могут их отрезать,
+    /// и тогда порталы закрываются, чтобы не вывести змейку за стену.
+    pub fn is_open(&self, config: &GameConfig) -> bool {
+        let limit = config.half_extents() - PORTAL_RADIUS;
+        [self.a, self.b]
+            .iter()
+            .all(|end| end.x.abs() < limit.x && end.y.abs() < limit.y)
+    }
+
+    /// Входы порталов и смещение от каждого входа к парному выходу.
+    pub fn jumps(&self) -> [(Vec2, Vec2); 2] {
+        [(self.a, self.b - self.a), (self.b, self.a - self.b)]
+    }
+
+    /// Смещение к парному выходу, если точка попала в один из порталов.
+    pub fn jump_from(&self, position: Vec2) -> Option<Vec2> {
+        self.jumps()
+            .into_iter()
+            .find(|&(entry, _)| entry.distance(position) < PORTAL_RADIUS)
+            .map(|(_, shift)| shift)
+    }
+
+    /// Порталы отображаются одинаковыми кольцами, чтобы было видно, что они связаны.
+    pub fn draw(&self, config: &GameConfig, screen: &ScreenTransform) {
+        let radius_pixels = PORTAL_RADIUS * screen.pixels_per_meter;
+        for end in [self.a, self.b] {
+            let screen_pos = screen.to_screen(end);
+            draw_circle_lines(
+                screen_pos.x,
+                screen_pos.y,
+                radius_pixels,
+                PORTAL_RING_THICKNESS,
+                config.theme().portal,
+            );
+        }
+    }
+}
+
+impl Snake {
+    /// Если голова попала в портал, то переносим её к парному выходу, сохраняя направление.
+    /// Сегменты переходят через портал следом за головой, когда до него доберутся.
+    pub fn enter_portal(&mut self, portal: &Portal, dt: f32) -> bool {
+        self.portal_cooldown = (self.portal_cooldown - dt).max(0.0);
+        if self.portal_cooldown > 0.0 {
+            return false;
+        }
+        let Some(shift) = portal.jump_from(self.head.position()) else {
+            return false;
+        };
+        self.head.unit.position += shift;
+        self.portal = Some(*portal);
+        self.portal_cooldown = PORTAL_COOLDOWN;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::random::RNG_LOCK;
+
+    fn portal() -> Portal {
+        Portal {
+            a: Vec2::new(-0.3, 0.0),
+            b: Vec2::new(0.4, 0.5),
+        }
+    }
+
+    #[test]
+    fn crowded_field_gets_closed_portals() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        rand::srand(2);
+        let config = GameConfig::default();
+        // Препятствие во всё поле не оставляет места ни одному порталу.
+        let wall = Obstacle {
+            position: Vec2::ZERO,
+            radius: config.field_size,
+        };
+        let portal = Portal::respawn(&config, &[wall]);
+        assert_eq!(portal, Portal::closed());
+        assert!(!portal.is_open(&config));
+
+        let portal = Portal::respawn(&config, &[]);
+        assert!(portal.is_open(&config));
+        assert!(portal.a.distance(portal.b) > config.half_size());
+    }
+
+    #[test]
+    fn head_jumps_to_paired_portal_once() {
+        let portal = portal();
+        let mut snake = Snake::default();
+        snake.head.unit.position = portal.a;
+        snake.head.direction = Vec2::Y;
+
+        assert!(snake.enter_portal(&portal, 0.01));
+        assert!(snake.head.position().distance(portal.b) < 1e-6);
+        assert_eq!(snake.head.direction, Vec2::Y);
+
+        // Сразу же обратно в портал не затягивает.
+        assert!(!snake.enter_portal(&portal, 0.01));
+        assert!(snake.enter_portal(&portal, PORTAL_COOLDOWN));
+        assert!(snake.head.position().distance(portal.a) < 1e-6);
+    }
+
+    #[test]
+    fn body_follows_through_portal_without_stretching() {
+        let config = GameConfig::default();
+        let portal = portal();
+        let mut snake = Snake::with_length(10, &config.tuning);
+        snake.move_to(Vec2::new(-0.8, 0.0));
+        for _ in 0..300 {
+            snake.go(0.01, 0.0, &config);
+            snake.enter_portal(&portal, 0.01);
+        }
+
+        // Голова вышла из парного портала, и все сегменты прошли за ней.
+        assert!(snake.head.position().y > portal.b.y - PORTAL_RADIUS);
+        let segments: Vec<_> = snake.segments().collect();
+        for pair in segments.windows(2) {
+            assert!(pair[0].distance(pair[1]) < 2.0 * snake.radius + PORTAL_RADIUS);
+        }
+    }
+}
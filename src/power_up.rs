@@ -0,0 +1,133 @@
+//! Бонусы, которые на время меняют скорость змейки, управление ею, делают её неуязвимой
+//! или притягивают к ней фрукты.
+
+use macroquad::prelude::*;
+
+use crate::config::GameConfig;
+use crate::random::{rand_f32, random_position};
+use crate::render::ScreenTransform;
+
+/// Радиус бонуса.
+pub const POWER_UP_RADIUS: f32 = 0.05;
+
+/// Вероятность появления бонуса за секунду, пока на поле нет другого бонуса.
+pub const POWER_UP_CHANCE_PER_SEC: f32 = 0.1;
+
+/// Во сколько раз ускоряется змейка, подобрав ускорение.
+pub const SPEED_BOOST_FACTOR: f32 = 1.8;
+
+/// Сколько секунд действует ускорение.
+pub const SPEED_BOOST_DURATION: f32 = 3.0;
+
+/// Во сколько раз замедляется змейка, подобрав замедление.
+pub const SLOW_MOTION_FACTOR: f32 = 0.5;
+
+/// Сколько секунд действует замедление.
+pub const SLOW_MOTION_DURATION: f32 = 4.0;
+
+/// Сколько секунд повороты змейки перевёрнуты.
+pub const REVERSE_CONTROLS_DURATION: f32 = 5.0;
+
+/// Сколько секунд змейка проходит сквозь себя.
+pub const INVINCIBILITY_DURATION: f32 = 4.0;
+
+/// Сколько секунд действует магнит для фруктов.
+pub const MAGNET_DURATION: f32 = 5.0;
+
+/// Разновидность бонуса.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    /// Временное ускорение змейки.
+    SpeedBoost,
+    /// Временное замедление змейки, чтобы было больше времени на реакцию.
+    SlowMotion,
+    /// Временно перевёрнутое управление: поворот налево поворачивает направо и наоборот.
+    ReverseControls,
+    /// Временная неуязвимость: голова проходит сквозь собственное тело, но не сквозь стены.
+    Invincibility,
+    /// Магнит: фрукты поблизости сами ползут к голове змейки.
+    Magnet,
+}
+
+impl PowerUpKind {
+    /// Случайная разновидность бонуса.
+    pub fn random() -> Self {
+        let kinds = [
+            Self::SpeedBoost,
+            Self::SlowMotion,
+            Self::ReverseControls,
+            Self::Invincibility,
+            Self::Magnet,
+        ];
+        kinds[((rand_f32() * kinds.len() as f32) as usize).min(kinds.len() - 1)]
+    }
+
+    /// Цвет бонуса.
+    pub fn color(self) -> Color {
+        match self {
+            Self::SpeedBoost => BLUE,
+            Self::SlowMotion => PINK,
+            Self::ReverseControls => PURPLE,
+            Self::Invincibility => GOLD,
+            Self::Magnet => DARKBLUE,
+        }
+    }
+
+    /// Во сколько раз бонус меняет скорость змейки.
+    pub fn speed_factor(self) -> f32 {
+        match self {
+            Self::SpeedBoost => SPEED_BOOST_FACTOR,
+            Self::SlowMotion => SLOW_MOTION_FACTOR,
+            Self::ReverseControls | Self::Invincibility | Self::Magnet => 1.0,
+        }
+    }
+
+    /// На что умножается поворот змейки, пока действует бонус.
+    pub fn rotation_factor(self) -> f32 {
+        match self {
+            Self::ReverseControls => -1.0,
+            Self::SpeedBoost | Self::SlowMotion | Self::Invincibility | Self::Magnet => 1.0,
+        }
+    }
+
+    /// Сколько секунд действует бонус.
+    pub fn duration(self) -> f32 {
+        match self {
+            Self::SpeedBoost => SPEED_BOOST_DURATION,
+            Self::SlowMotion => SLOW_MOTION_DURATION,
+            Self::ReverseControls => REVERSE_CONTROLS_DURATION,
+            Self::Invincibility => INVINCIBILITY_DURATION,
+            Self::Magnet => MAGNET_DURATION,
+        }
+    }
+}
+
+/// Действующий эффект подобранного бонуса.
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    /// Сколько секунд эффект ещё действует.
+    pub time_left: f32,
+}
+
+/// Бонус, который изредка появляется на поле.
+pub struct PowerUp {
+    pub position: Vec2,
+    pub kind: PowerUpKind,
+}
+
+impl PowerUp {
+    /// Бонус появляется в случайном месте игрового поля.
+    pub fn respawn(config: &GameConfig) -> Self {
+        Self {
+            position: random_position(config),
+            kind: PowerUpKind::random(),
+        }
+    }
+
+    /// Бонус отображается в виде круга цвета своей разновидности.
+    pub fn draw(&self, screen: &ScreenTransform) {
+        let radius_pixels = POWER_UP_RADIUS * screen.pixels_per_meter;
+        let screen_pos = screen.to_screen(self.position);
+        draw_circle(screen_pos.x, screen_pos.y, radius_pixels, self.kind.color());
+    }
+}
@@ -0,0 +1,66 @@
+//! Генератор случайных чисел.
+
+use macroquad::prelude::*;
+
+#[cfg(test)]
+use std::sync::Mutex;
+
+use crate::config::GameConfig;
+
+/// Переменная окружения, в которой можно задать зерно генератора случайных чисел.
+pub const SEED_ENV_VAR: &str = "SNAKE_SEED";
+
+/// Зерно генератора случайных чисел.
+/// Берём его из переменной окружения `SNAKE_SEED`, а если она не задана - из текущего времени.
+/// Запуски с одинаковым зерном раскладывают фрукты одинаково.
+pub fn rng_seed() -> u64 {
+    std::env::var(SEED_ENV_VAR)
+        .ok()
+        .and_then(|seed| seed.trim().parse().ok())
+        .unwrap_or_else(|| miniquad::date::now() as u64)
+}
+
+/// Возвращает случайное число от 0.0 до 1.0
+pub fn rand_f32() -> f32 {
+    (rand::rand() as f64 / u32::MAX as f64) as f32
+}
+
+/// Источник случайных чисел от 0.0 до 1.0.
+/// Игра берёт их из общего генератора macroquad, а тесты могут подставить заранее заданные.
+pub trait RngSource {
+    fn next_f32(&mut self) -> f32;
+}
+
+/// Общий генератор macroquad, которым пользуется игра.
+pub struct GlobalRng;
+
+impl RngSource for GlobalRng {
+    fn next_f32(&mut self) -> f32 {
+        rand_f32()
+    }
+}
+
+/// Источником может быть и просто замыкание, выдающее числа по очереди.
+impl<F: FnMut() -> f32> RngSource for F {
+    fn next_f32(&mut self) -> f32 {
+        self()
+    }
+}
+
+/// Возвращает случайную позицию в игровом поле.
+pub fn random_position(config: &GameConfig) -> Vec2 {
+    random_position_with(config, &mut GlobalRng)
+}
+
+/// Случайная позиция в игровом поле по числам из `rng`.
+pub fn random_position_with(config: &GameConfig, rng: &mut impl RngSource) -> Vec2 {
+    let half = config.half_extents();
+    let x = rng.next_f32() * 2.0 - 1.0;
+    let y = rng.next_f32() * 2.0 - 1.0;
+    Vec2::new(x, y) * half
+}
+
+/// Генератор случайных чисел общий для всех тестов, а тесты идут параллельно.
+/// Тесты, которым важна последовательность случайных чисел, берут эту блокировку.
+#[cfg(test)]
+pub static RNG_LOCK: Mutex<()> = Mutex::new(());
@@ -0,0 +1,640 @@
+//! Вывод на экран: цветовые темы, камера, перевод координат поля в координаты окна
+//! и элементы интерфейса.
+
+use macroquad::prelude::*;
+use macroquad::rand::RandGenerator;
+
+use std::fmt::Display;
+
+use crate::config::GameConfig;
+use crate::fruit::Fruit;
+use crate::lang::{write_filled, Strings};
+use crate::player::Player;
+use crate::snake::SELF_HIT_SCALE;
+
+/// Загружаем текстуру.
+/// Если файл не удалось загрузить, то рисуем без неё.
+pub async fn load_optional_texture(path: &str) -> Option<Texture2D> {
+    match load_texture(path).await {
+        Ok(texture) => Some(texture),
+        Err(e) => {
+            eprintln!("Failed to load texture {path}: {e}");
+            None
+        }
+    }
+}
+
+/// Как быстро камера догоняет голову змейки. Чем больше, тем резче.
+pub const CAMERA_SMOOTHING: f32 = 4.0;
+
+/// Цветовая тема: цвета фона, поля, змеек, фруктов и текста.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    /// Номер темы в `Strings::themes`, откуда берётся её название.
+    pub id: usize,
+    pub background: Color,
+    pub field: Color,
+    /// Цвета змеек первого и второго игрока.
+    pub snakes: [Color; 2],
+    pub fruit: Color,
+    pub golden_fruit: Color,
+    pub shrinking_fruit: Color,
+    /// Рисовать ли на фруктах значки, чтобы разные фрукты отличались не только цветом.
+    pub fruit_markers: bool,
+    pub obstacle: Color,
+    pub portal: Color,
+    /// Площадки, которые ускоряют и замедляют змейку.
+    pub fast_pad: Color,
+    pub slow_pad: Color,
+    pub text: Color,
+}
+
+impl Theme {
+    /// Название темы для меню.
+    pub fn name(&self, strings: &Strings) -> &'static str {
+        strings.themes[self.id]
+    }
+}
+
+/// Темы, которые можно переключать во время игры. Первая используется по умолчанию.
+pub const THEMES: [Theme; 3] = [
+    Theme {
+        id: 0,
+        background: LIGHTGRAY,
+        field: GREEN,
+        snakes: [WHITE, ORANGE],
+        fruit: RED,
+        golden_fruit: GOLD,
+        shrinking_fruit: PURPLE,
+        fruit_markers: false,
+        obstacle: DARKGRAY,
+        portal: BLUE,
+        fast_pad: Color::new(0.7, 1.0, 0.2, 1.0),
+        slow_pad: Color::new(0.2, 0.4, 1.0, 1.0),
+        text: BLACK,
+    },
+    Theme {
+        id: 1,
+        background: Color::new(0.08, 0.08, 0.1, 1.0),
+        field: Color::new(0.12, 0.22, 0.14, 1.0),
+        snakes: [LIGHTGRAY, ORANGE],
+        fruit: Color::new(0.9, 0.3, 0.3, 1.0),
+        golden_fruit: GOLD,
+        shrinking_fruit: PURPLE,
+        fruit_markers: false,
+        obstacle: Color::new(0.35, 0.35, 0.4, 1.0),
+        portal: SKYBLUE,
+        fast_pad: Color::new(0.3, 0.9, 0.4, 1.0),
+        slow_pad: Color::new(0.3, 0.5, 1.0, 1.0),
+        text: WHITE,
+    },
+    Theme {
+        id: 2,
+        background: BLACK,
+        field: Color::new(0.06, 0.0, 0.16, 1.0),
+        snakes: [Color::new(0.0, 1.0, 0.6, 1.0), MAGENTA],
+        fruit: Color::new(1.0, 0.2, 0.4, 1.0),
+        golden_fruit: YELLOW,
+        shrinking_fruit: PURPLE,
+        fruit_markers: false,
+        obstacle: Color::new(0.3, 0.3, 0.7, 1.0),
+        portal: Color::new(1.0, 0.5, 0.0, 1.0),
+        fast_pad: Color::new(0.2, 1.0, 0.2, 1.0),
+        slow_pad: Color::new(0.2, 0.4, 1.0, 1.0),
+        text: Color::new(0.0, 1.0, 1.0, 1.0),
+    },
+];
+
+/// Палитра для тех, кто плохо различает красный и зелёный: синее поле, жёлтые фрукты
+/// и значки на фруктах. Включается в настройках поверх выбранной темы.
+pub const COLORBLIND_THEME: Theme = Theme {
+    id: 3,
+    background: Color::new(0.1, 0.1, 0.12, 1.0),
+    field: Color::new(0.1, 0.25, 0.55, 1.0),
+    snakes: [WHITE, Color::new(1.0, 0.55, 0.0, 1.0)],
+    fruit: Color::new(1.0, 0.85, 0.1, 1.0),
+    golden_fruit: Color::new(1.0, 0.45, 0.0, 1.0),
+    shrinking_fruit: Color::new(1.0, 0.5, 0.8, 1.0),
+    fruit_markers: true,
+    obstacle: Color::new(0.7, 0.7, 0.7, 1.0),
+    portal: Color::new(0.6, 0.9, 1.0, 1.0),
+    fast_pad: Color::new(0.9, 0.9, 0.9, 1.0),
+    slow_pad: Color::new(0.05, 0.1, 0.3, 1.0),
+    text: WHITE,
+};
+
+/// Камера задаёт, какая точка поля окажется в центре экрана.
+#[derive(Clone, Copy, Default)]
+pub struct Camera {
+    pub center: Vec2,
+    /// Смещение всего изображения от тряски экрана в этом кадре.
+    pub shake_offset: Vec2,
+}
+
+impl Camera {
+    /// Плавно смещаем камеру к заданной точке.
+    /// Если поле помещается на экране целиком, то камера стоит в его центре.
+    pub fn follow(&mut self, target: Vec2, dt: f32, field_fits: bool) {
+        self.center = if field_fits {
+            Vec2::ZERO
+        } else {
+            self.center.lerp(target, (CAMERA_SMOOTHING * dt).min(1.0))
+        };
+    }
+}
+
+/// Размер окна в пикселях.
+fn window_size() -> Vec2 {
+    Vec2::new(screen_width(), screen_height())
+}
+
+/// Перевод координат игрового поля в координаты окна заданного размера и обратно.
+/// Считается один раз за кадр и передаётся во все функции рисования.
+pub struct ScreenTransform {
+    /// Отступ видимой части поля от краёв окна. Поле вписано в окно с сохранением
+    /// своей формы, поэтому по одной из осей остаются пустые полосы.
+    pub offset: Vec2,
+    /// Для рисования нам потребуются размеры в пикселях.
+    /// Масштаб такой, чтобы видимая часть поля помещалась в окно целиком, не теряя своей формы.
+    pub pixels_per_meter: f32,
+    /// Точка поля в верхнем левом углу видимой части, если смотреть от камеры.
+    pub top_left: Vec2,
+}
+
+impl ScreenTransform {
+    pub fn new(window: Vec2, config: &GameConfig) -> Self {
+        // Масштаб один для обеих осей, чтобы поле не растягивалось.
+        // Берём его по той оси, по которой поле упирается в край окна,
+        // а по другой оси поле ставим посередине.
+        let view = config.view_extents();
+        let pixels_per_meter = (window / view).min_element();
+        let half = view / 2.0;
+        let camera = config.camera.center - config.camera.shake_offset;
+        Self {
+            offset: (window - view * pixels_per_meter) / 2.0,
+            pixels_per_meter,
+            top_left: camera + Vec2::new(-half.x, half.y),
+        }
+    }
+
+    /// Перевод для текущего окна и камеры.
+    pub fn for_window(config: &GameConfig) -> Self {
+        Self::new(window_size(), config)
+    }
+
+    /// Масштаб по осям. Ось Y в окне направлена вниз, а на поле вверх.
+    fn scale(&self) -> Vec2 {
+        Vec2::new(1.0, -1.0) * self.pixels_per_meter
+    }
+
+    /// Из координат поля в координаты окна.
+    pub fn to_screen(&self, pos: Vec2) -> Vec2 {
+        (pos - self.top_left) * self.scale() + self.offset
+    }
+
+    /// Из координат окна в координаты поля.
+    pub fn to_world(&self, screen: Vec2) -> Vec2 {
+        (screen - self.offset) / self.scale() + self.top_left
+    }
+}
+
+/// Переводим координаты окна обратно в координаты игрового поля, например чтобы узнать,
+/// на какую точку поля указывает мышь.
+pub fn to_world_coords(screen: Vec2, config: &GameConfig) -> Vec2 {
+    ScreenTransform::for_window(config).to_world(screen)
+}
+
+/// Сколько секунд трясётся экран, когда змейка разбилась.
+pub const SHAKE_DURATION: f32 = 0.3;
+
+/// Наибольшее смещение изображения при тряске экрана.
+pub const SHAKE_AMPLITUDE: f32 = 0.03;
+
+/// Случайное смещение изображения при тряске экрана.
+/// Тряска затухает по мере того, как кончается оставшееся время `shake`.
+/// Случайные числа берём из отдельного генератора, чтобы не сбивать общий.
+pub fn shake_offset(shake: f32, rng: &RandGenerator) -> Vec2 {
+    let amplitude = SHAKE_AMPLITUDE * (shake / SHAKE_DURATION).clamp(0.0, 1.0);
+    Vec2::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0)) * amplitude
+}
+
+/// Рисуем игровое поле.
+pub fn draw_field(config: &GameConfig, screen: &ScreenTransform) {
+    // Координаты верхнего левого угла поля в пикселях.
+    let half = config.half_extents();
+    let top_left = screen.to_screen(Vec2::new(-half.x, half.y));
+    // Размер поля в пикселях.
+    let size = screen.pixels_per_meter * 2.0 * half;
+
+    // Рисуем поле в виде прямоугольника цвета темы.
+    draw_rectangle(top_left.x, top_left.y, size.x, size.y, config.theme().field);
+}
+
+/// Шаг линий сетки, которую можно наложить на поле.
+pub const GRID_LINE_STEP: f32 = 0.2;
+
+/// Прозрачность линий сетки.
+pub const GRID_LINE_ALPHA: f32 = 0.15;
+
+/// Рисуем поверх поля бледную сетку, не выходя за его края.
+/// Нужна для отладки движения и выравнивания.
+pub fn draw_grid(config: &GameConfig, screen: &ScreenTransform) {
+    let half = config.half_extents();
+    let extents = config.field_extents();
+    let color = Color {
+        a: GRID_LINE_ALPHA,
+        ..config.theme().text
+    };
+    // Линии привязаны к полю целиком, поэтому не сдвигаются вместе со стенами.
+    let draw = |from: Vec2, to: Vec2| {
+        let from = screen.to_screen(from);
+        let to = screen.to_screen(to);
+        draw_line(from.x, from.y, to.x, to.y, 1.0, color);
+    };
+    for i in 0..=(extents.x / GRID_LINE_STEP).round() as usize {
+        let x = -extents.x / 2.0 + i as f32 * GRID_LINE_STEP;
+        if x.abs() <= half.x + f32::EPSILON {
+            draw(Vec2::new(x, -half.y), Vec2::new(x, half.y));
+        }
+    }
+    for i in 0..=(extents.y / GRID_LINE_STEP).round() as usize {
+        let y = -extents.y / 2.0 + i as f32 * GRID_LINE_STEP;
+        if y.abs() <= half.y + f32::EPSILON {
+            draw(Vec2::new(-half.x, y), Vec2::new(half.x, y));
+        }
+    }
+}
+
+/// Толщина контуров отладочного слоя столкновений в пикселях.
+const COLLISION_LINE_THICKNESS: f32 = 1.5;
+
+/// Рисуем поверх поля контуры, по которым на самом деле проверяются столкновения.
+/// Контуры сегментов и фруктов берут те же радиусы, что и проверки, поэтому видно,
+/// если нарисованная змейка или фрукт разошлись со своими границами.
+/// Сегменты, с которыми сейчас проверяется столкновение головы, обведены красным
+/// кругом: голова разбивается, если её центр (белая точка) попадёт в такой круг.
+pub fn draw_collision_overlay(players: &[Player], fruits: &[Fruit], screen: &ScreenTransform) {
+    let ppm = screen.pixels_per_meter;
+    let outline = |position: Vec2, radius: f32, color: Color| {
+        let screen_pos = screen.to_screen(position);
+        draw_circle_lines(
+            screen_pos.x,
+            screen_pos.y,
+            radius * ppm,
+            COLLISION_LINE_THICKNESS,
+            color,
+        );
+    };
+    for fruit in fruits {
+        outline(fruit.position, fruit.radius, YELLOW);
+    }
+    for player in players {
+        let snake = &player.snake;
+        for position in snake.segments() {
+            outline(position, snake.radius, SKYBLUE);
+        }
+        for index in snake.self_collision_candidates() {
+            outline(
+                snake.units[index].position,
+                snake.radius * SELF_HIT_SCALE,
+                RED,
+            );
+        }
+        let head = screen.to_screen(snake.head.position());
+        draw_circle(head.x, head.y, 2.0, WHITE);
+    }
+}
+
+/// Сторона квадратной миникарты в пикселях.
+pub const MINIMAP_SIZE: f32 = 150.0;
+
+/// Отступ миникарты от краёв окна в пикселях.
+pub const MINIMAP_MARGIN: f32 = 20.0;
+
+/// Радиус фрукта на миникарте в пикселях. Настоящий размер там было бы не разглядеть.
+pub const MINIMAP_FRUIT_RADIUS: f32 = 2.0;
+
+/// Переводим координаты поля в координаты миникарты с левым верхним углом в `top_left`.
+/// Миникарта всегда показывает поле целиком, поэтому камера и тряска на неё не влияют.
+/// Вытянутое поле вписываем в миникарту по большей стороне.
+pub fn to_minimap_coords(pos: Vec2, top_left: Vec2, config: &GameConfig) -> Vec2 {
+    let scale = MINIMAP_SIZE / config.field_extents().max_element();
+    let center = top_left + Vec2::splat(MINIMAP_SIZE / 2.0);
+    center + Vec2::new(pos.x, -pos.y) * scale
+}
+
+/// Непрозрачность тумана.
+pub const FOG_ALPHA: f32 = 0.92;
+
+/// Из скольких кусков состоит кольцо тумана вокруг видимого круга.
+const FOG_SEGMENTS: usize = 64;
+
+/// Докуда от `center` должен доставать туман, чтобы закрыть окно до самых углов.
+fn fog_outer_radius(center: Vec2, window: Vec2) -> f32 {
+    [
+        Vec2::ZERO,
+        Vec2::new(window.x, 0.0),
+        Vec2::new(0.0, window.y),
+        window,
+    ]
+    .into_iter()
+    .map(|corner| corner.distance(center))
+    .fold(0.0, f32::max)
+}
+
+/// Закрываем туманом всё окно, кроме круга радиусом `radius` пикселей вокруг `center`.
+/// Туман - кольцо из трапеций от края круга до углов окна, поэтому круг остаётся
+/// по-настоящему прозрачным. Рисуется поверх поля, но под интерфейсом.
+pub fn draw_fog(center: Vec2, radius: f32) {
+    let outer = fog_outer_radius(center, window_size()) + 1.0;
+    let color = Color::new(0.0, 0.0, 0.0, FOG_ALPHA);
+    let point = |index: usize, distance: f32| {
+        let angle = std::f32::consts::TAU * index as f32 / FOG_SEGMENTS as f32;
+        center + Vec2::from_angle(angle) * distance
+    };
+    for index in 0..FOG_SEGMENTS {
+        let (inner_a, inner_b) = (point(index, radius), point(index + 1, radius));
+        let (outer_a, outer_b) = (point(index, outer), point(index + 1, outer));
+        draw_triangle(inner_a, outer_a, outer_b, color);
+        draw_triangle(inner_a, outer_b, inner_b, color);
+    }
+}
+
+/// Рисуем миникарту в правом нижнем углу окна: стены, фрукты и змеек ломаными линиями.
+pub fn draw_minimap(config: &GameConfig, players: &[Player], fruits: &[Fruit]) {
+    let top_left = Vec2::new(
+        screen_width() - MINIMAP_SIZE - MINIMAP_MARGIN,
+        screen_height() - MINIMAP_SIZE - MINIMAP_MARGIN,
+    );
+    let theme = config.theme();
+    let background = Color {
+        a: 0.6,
+        ..theme.background
+    };
+    draw_rectangle(
+        top_left.x,
+        top_left.y,
+        MINIMAP_SIZE,
+        MINIMAP_SIZE,
+        background,
+    );
+
+    // Стены рисуем там, где они сейчас, чтобы было видно, как сжимается поле.
+    let half = config.half_extents();
+    let walls = to_minimap_coords(Vec2::new(-half.x, half.y), top_left, config);
+    let walls_size = to_minimap_coords(Vec2::new(half.x, -half.y), top_left, config) - walls;
+    draw_rectangle_lines(
+        walls.x,
+        walls.y,
+        walls_size.x,
+        walls_size.y,
+        2.0,
+        theme.text,
+    );
+
+    for fruit in fruits {
+        let pos = to_minimap_coords(fruit.position, top_left, config);
+        draw_circle(pos.x, pos.y, MINIMAP_FRUIT_RADIUS, fruit.kind.color(theme));
+    }
+
+    // Сегменты, разнесённые по разным краям поля, не соединяем.
+    for player in players {
+        let snake = &player.snake;
+        let positions: Vec<_> = snake.segments().collect();
+        for pair in positions.windows(2) {
+            if pair[0].distance(pair[1]) > 4.0 * snake.radius {
+                continue;
+            }
+            let from = to_minimap_coords(pair[0], top_left, config);
+            let to = to_minimap_coords(pair[1], top_left, config);
+            draw_line(from.x, from.y, to.x, to.y, 2.0, snake.color);
+        }
+        let head = to_minimap_coords(snake.head.position(), top_left, config);
+        draw_circle(head.x, head.y, MINIMAP_FRUIT_RADIUS * 1.5, snake.color);
+    }
+}
+
+/// Рисуем шаблон надписи с подставленными `args`, собранный в строке `buffer`.
+/// Строка переиспользуется из кадра в кадр, поэтому текст, который меняется каждый кадр,
+/// не выделяет память заново.
+pub fn draw_formatted(
+    buffer: &mut String,
+    template: &str,
+    args: &[&dyn Display],
+    position: Vec2,
+    font_size: f32,
+    color: Color,
+) {
+    buffer.clear();
+    write_filled(buffer, template, args);
+    draw_text(buffer, position.x, position.y, font_size, color);
+}
+
+/// Рисуем текст по центру окна по горизонтали.
+pub fn draw_centered_text(text: &str, y: f32, font_size: f32, color: Color) {
+    let size = measure_text(text, None, font_size as _, 1.0);
+    draw_text(
+        text,
+        (screen_width() - size.width) / 2.0,
+        y,
+        font_size,
+        color,
+    );
+}
+
+/// Рисуем пункт меню по центру окна. Выбранный пункт крупнее и отмечен стрелками.
+pub fn draw_menu_option(text: &str, y: f32, selected: bool, color: Color) {
+    if selected {
+        draw_centered_text(&format!("> {text} <"), y, 32.0, color);
+    } else {
+        draw_centered_text(text, y, 24.0, color);
+    }
+}
+
+/// За сколько секунд сглаживается показание счётчика кадров.
+pub const FPS_SMOOTHING_WINDOW: f32 = 0.5;
+
+/// Счётчик кадров в секунду, сглаженный, чтобы число не прыгало от кадра к кадру.
+pub struct FpsCounter {
+    /// Сглаженное время кадра.
+    pub frame_time: f32,
+}
+
+impl FpsCounter {
+    pub fn new() -> Self {
+        Self {
+            frame_time: 1.0 / 60.0,
+        }
+    }
+
+    /// Учитываем время очередного кадра.
+    pub fn update(&mut self, dt: f32) {
+        let weight = (dt / FPS_SMOOTHING_WINDOW).min(1.0);
+        self.frame_time += (dt - self.frame_time) * weight;
+    }
+
+    /// Сглаженное число кадров в секунду.
+    pub fn fps(&self) -> f32 {
+        1.0 / self.frame_time.max(f32::EPSILON)
+    }
+}
+
+/// Ширина и высота полоски сил в пикселях.
+pub const STAMINA_BAR_SIZE: Vec2 = Vec2::new(120.0, 8.0);
+
+/// Рисуем полоску сил на ускорение с левым верхним углом в заданной точке экрана.
+/// Пока силы не восстановились после истощения, полоска красная.
+pub fn draw_stamina_bar(top_left: Vec2, player: &Player, color: Color) {
+    let background = Color { a: 0.3, ..color };
+    let fill = if player.exhausted { RED } else { color };
+    let (width, height) = (STAMINA_BAR_SIZE.x, STAMINA_BAR_SIZE.y);
+    draw_rectangle(top_left.x, top_left.y, width, height, background);
+    draw_rectangle(top_left.x, top_left.y, width * player.stamina, height, fill);
+}
+
+/// Рисуем полоску сытости с левым верхним углом в заданной точке экрана.
+/// После еды полоска полная, а когда змейка вот-вот потеряет сегмент, она краснеет.
+pub fn draw_hunger_bar(top_left: Vec2, player: &Player, color: Color) {
+    let background = Color { a: 0.3, ..color };
+    let satiety = player.satiety().clamp(0.0, 1.0);
+    let fill = if satiety < 0.25 { RED } else { color };
+    let (width, height) = (STAMINA_BAR_SIZE.x, STAMINA_BAR_SIZE.y);
+    draw_rectangle(top_left.x, top_left.y, width, height, background);
+    draw_rectangle(top_left.x, top_left.y, width * satiety, height, fill);
+}
+
+/// Рисуем значок часов с центром в заданной точке экрана.
+pub fn draw_clock_icon(center: Vec2, radius: f32) {
+    draw_circle(center.x, center.y, radius, WHITE);
+    draw_circle_lines(center.x, center.y, radius, 3.0, BLACK);
+    // Часовая стрелка смотрит на три часа, а минутная на двенадцать.
+    draw_line(
+        center.x,
+        center.y,
+        center.x + radius * 0.5,
+        center.y,
+        3.0,
+        BLACK,
+    );
+    draw_line(
+        center.x,
+        center.y,
+        center.x,
+        center.y - radius * 0.8,
+        2.0,
+        BLACK,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_follows_head_only_on_large_field() {
+        let target = Vec2::new(0.5, -0.5);
+        let mut camera = Camera::default();
+        camera.follow(target, 0.1, true);
+        assert_eq!(camera.center, Vec2::ZERO);
+
+        // Камера догоняет цель постепенно, не перескакивая через неё.
+        camera.follow(target, 0.1, false);
+        assert!(camera.center.length() > 0.0);
+        assert!(camera.center.distance(target) < target.length());
+        for _ in 0..100 {
+            camera.follow(target, 0.1, false);
+        }
+        assert!(camera.center.distance(target) < 1e-4);
+    }
+
+    #[test]
+    fn minimap_shows_whole_field() {
+        let config = GameConfig {
+            field_size: 4.0,
+            ..GameConfig::default()
+        };
+        let top_left = Vec2::new(10.0, 20.0);
+        let center = top_left + Vec2::splat(MINIMAP_SIZE / 2.0);
+        assert_eq!(to_minimap_coords(Vec2::ZERO, top_left, &config), center);
+        // Верхний левый угол поля попадает в верхний левый угол миникарты, где бы ни была камера.
+        let corner = Vec2::new(-2.0, 2.0);
+        assert_eq!(to_minimap_coords(corner, top_left, &config), top_left);
+        let bottom_right = to_minimap_coords(-corner, top_left, &config);
+        assert_eq!(bottom_right, top_left + Vec2::splat(MINIMAP_SIZE));
+    }
+
+    #[test]
+    fn world_coords_undo_screen_coords() {
+        let config = GameConfig {
+            field_size: 3.0,
+            camera: Camera {
+                center: Vec2::new(0.4, -0.2),
+                shake_offset: Vec2::new(0.01, 0.02),
+            },
+            ..GameConfig::default()
+        };
+        let points = [
+            Vec2::ZERO,
+            Vec2::new(1.0, 0.5),
+            Vec2::new(-1.5, 1.5),
+            Vec2::new(0.3, -1.2),
+        ];
+        for window in [Vec2::new(800.0, 600.0), Vec2::new(600.0, 1000.0)] {
+            let transform = ScreenTransform::new(window, &config);
+            for point in points {
+                let back = transform.to_world(transform.to_screen(point));
+                assert!(back.distance(point) < 1e-4, "{point} -> {back} in {window}");
+            }
+        }
+
+        // Центр камеры попадает в центр окна, а поле вписывается по меньшей стороне.
+        let transform = ScreenTransform::new(Vec2::new(800.0, 600.0), &config);
+        let center = config.camera.center - config.camera.shake_offset;
+        assert!(
+            transform
+                .to_screen(center)
+                .distance(Vec2::new(400.0, 300.0))
+                < 1e-3
+        );
+        assert_eq!(transform.offset, Vec2::new(100.0, 0.0));
+    }
+
+    #[test]
+    fn fog_reaches_every_corner() {
+        let window = Vec2::new(800.0, 600.0);
+        assert_eq!(fog_outer_radius(Vec2::new(400.0, 300.0), window), 500.0);
+        // Голова у края окна: туман должен дотянуться до дальнего угла.
+        assert_eq!(fog_outer_radius(Vec2::ZERO, window), 1000.0);
+    }
+
+    #[test]
+    fn wide_field_is_letterboxed() {
+        let config = GameConfig {
+            field_size: 2.0,
+            field_aspect: 2.0,
+            ..GameConfig::default()
+        };
+        // Поле 4 на 2 упирается в окно по ширине, а сверху и снизу остаются полосы.
+        let transform = ScreenTransform::new(Vec2::new(800.0, 600.0), &config);
+        assert_eq!(transform.pixels_per_meter, 200.0);
+        assert_eq!(transform.offset, Vec2::new(0.0, 100.0));
+        let corner = Vec2::new(-2.0, 1.0);
+        assert_eq!(transform.to_screen(corner), Vec2::new(0.0, 100.0));
+        assert_eq!(transform.to_screen(-corner), Vec2::new(800.0, 500.0));
+
+        for point in [corner, -corner, Vec2::new(1.7, 0.4), Vec2::new(-0.3, -0.9)] {
+            let back = transform.to_world(transform.to_screen(point));
+            assert!(back.distance(point) < 1e-4, "{point} -> {back}");
+        }
+    }
+
+    #[test]
+    fn shake_fades_out() {
+        let rng = RandGenerator::new();
+        let strong = shake_offset(SHAKE_DURATION, &rng);
+        assert!(strong.x.abs() <= SHAKE_AMPLITUDE && strong.y.abs() <= SHAKE_AMPLITUDE);
+        let weak = shake_offset(SHAKE_DURATION / 10.0, &rng);
+        assert!(weak.length() <= SHAKE_AMPLITUDE * 0.1 * 2.0_f32.sqrt());
+        assert_eq!(shake_offset(0.0, &rng), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,264 @@
+//! Запись и воспроизведение раунда.
+//! Раунд полностью определяется зерном генератора случайных чисел, настройками
+//! и тем, сколько длился каждый кадр и как в нём управляли змейками.
+
+use std::fs;
+
+use crate::config::{Difficulty, GameConfig};
+use crate::player::PlayersMode;
+use crate::storage::data_file_path;
+
+/// Файл, в который сохраняется запись, рядом с исполняемым файлом игры.
+const REPLAY_FILE_NAME: &str = "replay.txt";
+
+/// Как игрок управлял змейкой в одном кадре.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerInput {
+    /// Скорость поворота.
+    pub rotation: f32,
+    /// Зажато ли ускорение.
+    pub boost: bool,
+}
+
+/// Что происходило в одном кадре: его длительность и управление змейками всех игроков.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub dt: f32,
+    pub inputs: Vec<PlayerInput>,
+}
+
+/// Запись раунда.
+#[derive(Debug, PartialEq)]
+pub struct Replay {
+    /// Зерно, которым инициализировали генератор в начале раунда.
+    pub seed: u64,
+    pub field_size: f32,
+    pub field_aspect: f32,
+    pub wrap_walls: bool,
+    pub sudden_death: bool,
+    pub timed: bool,
+    pub goal: bool,
+    pub classic: bool,
+    pub hunger: bool,
+    pub starting_length: usize,
+    pub mode: PlayersMode,
+    pub difficulty: Difficulty,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    /// Пустая запись раунда с заданными зерном и настройками.
+    pub fn new(seed: u64, config: &GameConfig, mode: PlayersMode) -> Self {
+        Self {
+            seed,
+            field_size: config.field_size,
+            field_aspect: config.field_aspect,
+            wrap_walls: config.wrap_walls,
+            sudden_death: config.sudden_death,
+            timed: config.timed,
+            goal: config.goal,
+            classic: config.classic,
+            hunger: config.hunger,
+            starting_length: config.starting_length,
+            mode,
+            difficulty: config.difficulty,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Переносим записанные настройки поля в `config`. Цветовую тему не трогаем.
+    pub fn apply_to(&self, config: &mut GameConfig) {
+        config.difficulty = self.difficulty;
+        config.field_size = self.field_size;
+        config.field_aspect = self.field_aspect;
+        config.wrap_walls = self.wrap_walls;
+        config.sudden_death = self.sudden_death;
+        config.timed = self.timed;
+        config.goal = self.goal;
+        config.classic = self.classic;
+        config.hunger = self.hunger;
+        config.starting_length = self.starting_length;
+    }
+
+    /// Запись в текстовом виде: в первой строке зерно и настройки, дальше по строке на кадр.
+    /// Игра на длину, классический режим, голод и форма поля появились позже остальных настроек,
+    /// поэтому записаны в конце первой строки.
+    /// Числа с плавающей точкой выводятся так, что при чтении получаются в точности те же значения.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{} {} {} {} {} {} {} {} {} {} {} {}\n",
+            self.seed,
+            self.field_size,
+            self.wrap_walls,
+            self.sudden_death,
+            self.timed,
+            self.starting_length,
+            self.mode as usize,
+            self.difficulty as usize,
+            self.goal,
+            self.classic,
+            self.hunger,
+            self.field_aspect
+        );
+        for frame in &self.frames {
+            text += &frame.dt.to_string();
+            for input in &frame.inputs {
+                text += &format!(" {} {}", input.rotation, input.boost);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Разбираем запись, сохранённую `to_text`. Если она испорчена, то возвращаем `None`.
+    /// В старых записях нет игры на длину, классического режима, голода и формы поля,
+    /// и они читаются как игра без них на квадратном поле.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let mut header = lines.next()?.split_whitespace();
+        let seed = header.next()?.parse().ok()?;
+        let field_size = header.next()?.parse().ok()?;
+        let wrap_walls = header.next()?.parse().ok()?;
+        let sudden_death = header.next()?.parse().ok()?;
+        let timed = header.next()?.parse().ok()?;
+        let starting_length = header.next()?.parse().ok()?;
+        let mode = *PlayersMode::ALL.get(header.next()?.parse::<usize>().ok()?)?;
+        let difficulty = *Difficulty::ALL.get(header.next()?.parse::<usize>().ok()?)?;
+        let mut optional_flag = || match header.next() {
+            Some(word) => word.parse().ok(),
+            None => Some(false),
+        };
+        let goal = optional_flag()?;
+        let classic = optional_flag()?;
+        let hunger = optional_flag()?;
+        let field_aspect = match header.next() {
+            Some(word) => word.parse().ok()?,
+            None => 1.0,
+        };
+
+        let frames = lines
+            .map(|line| {
+                let mut words = line.split_whitespace();
+                let dt = words.next()?.parse().ok()?;
+                let mut inputs = Vec::new();
+                while let Some(rotation) = words.next() {
+                    inputs.push(PlayerInput {
+                        rotation: rotation.parse().ok()?,
+                        boost: words.next()?.parse().ok()?,
+                    });
+                }
+                // Управление каждого кадра должно быть ровно у каждой змейки режима,
+                // иначе при просмотре оно досталось бы несуществующему игроку.
+                (inputs.len() == mode.players_count()).then_some(ReplayFrame { dt, inputs })
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            seed,
+            field_size,
+            field_aspect,
+            wrap_walls,
+            sudden_death,
+            timed,
+            goal,
+            classic,
+            hunger,
+            starting_length,
+            mode,
+            difficulty,
+            frames,
+        })
+    }
+
+    /// Сохраняем запись в файл.
+    /// Ошибку записи только сообщаем: из-за неё игра не должна падать.
+    pub fn save(&self) {
+        let Some(path) = data_file_path(REPLAY_FILE_NAME) else {
+            return;
+        };
+        match fs::write(&path, self.to_text()) {
+            Ok(()) => eprintln!("Replay saved to {}", path.display()),
+            Err(e) => eprintln!("Failed to save replay to {}: {e}", path.display()),
+        }
+    }
+
+    /// Читаем запись из файла, если он есть и его удалось разобрать.
+    pub fn load() -> Option<Self> {
+        let path = data_file_path(REPLAY_FILE_NAME)?;
+        let text = fs::read_to_string(&path).ok()?;
+        let replay = Self::from_text(&text);
+        if replay.is_none() {
+            eprintln!("Failed to parse replay from {}", path.display());
+        }
+        replay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(rotation: f32, boost: bool) -> PlayerInput {
+        PlayerInput { rotation, boost }
+    }
+
+    #[test]
+    fn text_round_trip_is_exact() {
+        let config = GameConfig {
+            field_size: 2.5,
+            field_aspect: 1.5,
+            wrap_walls: true,
+            sudden_death: true,
+            timed: true,
+            goal: true,
+            classic: true,
+            hunger: true,
+            starting_length: 5,
+            difficulty: Difficulty::Hard,
+            ..GameConfig::default()
+        };
+        let mut replay = Replay::new(1234567890123, &config, PlayersMode::VersusAi);
+        replay.frames = vec![
+            ReplayFrame {
+                dt: 1.0 / 60.0,
+                inputs: vec![input(0.0, false), input(-2.0, true)],
+            },
+            ReplayFrame {
+                dt: 0.016_666_668,
+                inputs: vec![input(1.234_567_9, true), input(0.1, false)],
+            },
+        ];
+
+        assert_eq!(Replay::from_text(&replay.to_text()), Some(replay));
+    }
+
+    #[test]
+    fn broken_text_is_rejected() {
+        assert_eq!(Replay::from_text(""), None);
+        assert_eq!(Replay::from_text("1 2 true false false 0 7 0\n"), None);
+        assert_eq!(Replay::from_text("1 2 true false false 0 0 3\n"), None);
+        assert_eq!(
+            Replay::from_text("1 2 true false false 0 0 1\n0.1 x false\n"),
+            None
+        );
+        assert_eq!(
+            Replay::from_text("1 2 true false false 0 0 1\n0.1 0.5\n"),
+            None
+        );
+        assert_eq!(
+            Replay::from_text("1 2 true false false 0 0 1 maybe\n"),
+            None
+        );
+        // В одиночной игре у кадра ровно одно управление.
+        assert_eq!(
+            Replay::from_text("1 2 true false false 0 0 1\n0.1 0.5 false 0.5 false\n"),
+            None
+        );
+
+        // Запись без игры на длину, классического режима, голода и формы поля
+        // читается как игра без них на квадратном поле.
+        let old = Replay::from_text("1 2 true false false 0 0 1\n").unwrap();
+        assert!(!old.goal && !old.classic && !old.hunger);
+        assert_eq!(old.field_aspect, 1.0);
+    }
+}
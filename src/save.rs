@@ -0,0 +1,273 @@
+//! Сохранение недоигранного раунда, чтобы выйти из игры и доиграть его потом.
+//! Раунд сохраняется в JSON. Бонусы, частицы и бонусный фрукт живут недолго,
+//! поэтому не сохраняются. Бомбы и так часто переезжают, поэтому раскладываются заново.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+
+use crate::bomb::spawn_bombs;
+use crate::config::Difficulty;
+use crate::fruit::Fruit;
+use crate::obstacle::Obstacle;
+use crate::pad::SpeedPad;
+use crate::player::{PlayersMode, RunStats};
+use crate::portal::Portal;
+use crate::simulation::{Game, NearMiss};
+use crate::snake::{Snake, SpatialGrid};
+
+/// Файл с сохранённым раундом рядом с исполняемым файлом игры.
+pub const SAVE_FILE_NAME: &str = "save.json";
+
+/// Версия формата сохранения. Сохранения других версий не загружаем.
+pub const SAVE_VERSION: u32 = 2;
+
+/// `Vec2` из macroquad не умеет сериализоваться, поэтому сохраняем его как пару чисел.
+pub mod vec2 {
+    use macroquad::prelude::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+        v.to_array().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+        <[f32; 2]>::deserialize(deserializer).map(Vec2::from)
+    }
+}
+
+/// Цвет из macroquad сохраняем как четыре числа.
+pub mod color {
+    use macroquad::prelude::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        [c.r, c.g, c.b, c.a].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        <[f32; 4]>::deserialize(deserializer).map(|[r, g, b, a]| Color::new(r, g, b, a))
+    }
+}
+
+/// Отношение сторон квадратного поля.
+fn square_aspect() -> f32 {
+    1.0
+}
+
+/// Что сохраняем об игроке. Управление восстанавливаем по режиму игры.
+#[derive(Serialize, Deserialize)]
+pub struct SavedPlayer {
+    pub snake: Snake,
+    pub score: u32,
+    pub stamina: f32,
+    #[serde(default)]
+    pub time_since_eaten: f32,
+    #[serde(default)]
+    pub stats: RunStats,
+}
+
+/// Недоигранный раунд.
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame {
+    pub version: u32,
+    pub field_size: f32,
+    /// В сохранениях до появления вытянутых полей поле всегда квадратное.
+    #[serde(default = "square_aspect")]
+    pub field_aspect: f32,
+    pub wrap_walls: bool,
+    pub sudden_death: bool,
+    pub timed: bool,
+    #[serde(default)]
+    pub goal: bool,
+    #[serde(default)]
+    pub classic: bool,
+    #[serde(default)]
+    pub hunger: bool,
+    pub starting_length: usize,
+    pub difficulty: Difficulty,
+    pub round_time: f32,
+    pub mode: PlayersMode,
+    pub players: Vec<SavedPlayer>,
+    pub fruits: Vec<Fruit>,
+    pub obstacles: Vec<Obstacle>,
+    #[serde(default)]
+    pub speed_pads: Vec<SpeedPad>,
+    pub portal: Portal,
+    /// Уровень, до которого дошли. Без него препятствия сменились бы сразу после загрузки.
+    #[serde(default)]
+    pub level: u32,
+}
+
+impl SavedGame {
+    /// Снимок раунда.
+    pub fn new(game: &Game) -> Self {
+        let config = &game.config;
+        let players = game
+            .players
+            .iter()
+            .map(|player| SavedPlayer {
+                snake: player.snake.clone(),
+                score: player.score,
+                stamina: player.stamina,
+                time_since_eaten: player.time_since_eaten,
+                stats: player.stats,
+            })
+            .collect();
+        Self {
+            version: SAVE_VERSION,
+            field_size: config.field_size,
+            field_aspect: config.field_aspect,
+            wrap_walls: config.wrap_walls,
+            sudden_death: config.sudden_death,
+            timed: config.timed,
+            goal: config.goal,
+            classic: config.classic,
+            hunger: config.hunger,
+            starting_length: config.starting_length,
+            difficulty: config.difficulty,
+            round_time: config.round_time,
+            mode: game.mode,
+            players,
+            fruits: game.fruits.clone(),
+            obstacles: game.obstacles.clone(),
+            speed_pads: game.speed_pads.clone(),
+            portal: game.portal,
+            level: game.level,
+        }
+    }
+
+    /// Восстанавливаем раунд в `game`. Цветовую тему не трогаем.
+    pub fn restore(self, game: &mut Game) {
+        let config = &mut game.config;
+        config.field_size = self.field_size;
+        config.field_aspect = self.field_aspect;
+        config.wrap_walls = self.wrap_walls;
+        config.sudden_death = self.sudden_death;
+        config.timed = self.timed;
+        config.goal = self.goal;
+        config.classic = self.classic;
+        config.hunger = self.hunger;
+        config.starting_length = self.starting_length;
+        config.difficulty = self.difficulty;
+        config.reset_tuning();
+        config.round_time = self.round_time;
+
+        game.mode = self.mode;
+        game.respawn_players();
+        for (player, saved) in game.players.iter_mut().zip(self.players) {
+            player.snake = saved.snake;
+            player.score = saved.score;
+            player.stamina = saved.stamina;
+            player.time_since_eaten = saved.time_since_eaten;
+            player.stats = saved.stats;
+
+            // Сетку не сохраняем, а раскладываем сегменты по ней заново.
+            let snake = &mut player.snake;
+            let field_size = game.config.field_extents().max_element();
+            snake.grid = SpatialGrid::new(field_size, snake.radius);
+            snake.grid.rebuild(&snake.units, field_size);
+            snake.update_speed();
+        }
+        game.fruits = self.fruits;
+        game.obstacles = self.obstacles;
+        game.speed_pads = self.speed_pads;
+        game.portal = self.portal;
+        game.level = self.level;
+        game.level_banner = 0.0;
+        game.bombs = spawn_bombs(&game.config, &game.players, &game.fruits);
+        game.power_up = None;
+        game.bonus = None;
+        game.particles.clear();
+        game.floating_texts.clear();
+        game.near_miss = NearMiss::default();
+    }
+
+    /// Раунд в виде JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("saved game is always serializable")
+    }
+
+    /// Разбираем раунд из JSON. Испорченные сохранения и сохранения другой версии отбрасываем.
+    pub fn from_json(text: &str) -> Option<Self> {
+        let saved: Self = serde_json::from_str(text).ok()?;
+        (saved.version == SAVE_VERSION).then_some(saved)
+    }
+
+    /// Сохраняем раунд в файл.
+    /// Ошибку записи только сообщаем: из-за неё игра не должна падать.
+    pub fn save(&self, path: &Path) {
+        match fs::write(path, self.to_json()) {
+            Ok(()) => eprintln!("Game saved to {}", path.display()),
+            Err(e) => eprintln!("Failed to save game to {}: {e}", path.display()),
+        }
+    }
+
+    /// Читаем раунд из файла, если он есть и его удалось разобрать.
+    pub fn load(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        let saved = Self::from_json(&text);
+        if saved.is_none() {
+            eprintln!("Ignoring incompatible saved game {}", path.display());
+        }
+        saved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::GameConfig;
+    use crate::random::RNG_LOCK;
+
+    fn saved_game(config: GameConfig) -> SavedGame {
+        let mut game = Game::new(config, PlayersMode::VersusAi);
+        game.players[1].score = 42;
+        for _ in 0..20 {
+            game.players[0].snake.add_unit();
+            game.players[0].snake.go(0.05, 1.0, &game.config);
+        }
+        SavedGame::new(&game)
+    }
+
+    #[test]
+    fn json_round_trip_restores_round() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        rand::srand(5);
+        let config = GameConfig {
+            timed: true,
+            round_time: 12.5,
+            ..GameConfig::default()
+        };
+        let saved = saved_game(config);
+        let positions: Vec<_> = saved.players[0].snake.segments().collect();
+        let fruit = saved.fruits[0].position;
+
+        let portal = saved.portal;
+        let loaded = SavedGame::from_json(&saved.to_json()).unwrap();
+        let mut game = Game::new(GameConfig::default(), PlayersMode::Single);
+        loaded.restore(&mut game);
+        assert_eq!(game.mode, PlayersMode::VersusAi);
+        assert!(game.config.timed);
+        assert_eq!(game.config.round_time, 12.5);
+        assert_eq!(game.players[1].score, 42);
+        let restored: Vec<_> = game.players[0].snake.segments().collect();
+        assert_eq!(restored, positions);
+        assert_eq!(game.fruits[0].position, fruit);
+        assert_eq!(game.portal, portal);
+        assert!(game.players[0].snake.death_cause(&game.config).is_none());
+    }
+
+    #[test]
+    fn incompatible_save_is_ignored() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut saved = saved_game(GameConfig::default());
+        saved.version = SAVE_VERSION + 1;
+        assert!(SavedGame::from_json(&saved.to_json()).is_none());
+        assert!(SavedGame::from_json("{\"version\": 1}").is_none());
+        assert!(SavedGame::from_json("not json").is_none());
+    }
+}
@@ -0,0 +1,125 @@
+//! Сценарии для проверки игры целиком, без окна.
+//! Сценарий ставит одну змейку на пустое поле, раскладывает нужные фрукты
+//! и прогоняет через `Game::step` заранее заданное управление.
+
+use macroquad::prelude::*;
+
+use crate::config::GameConfig;
+use crate::fruit::Fruit;
+use crate::player::PlayersMode;
+use crate::portal::Portal;
+use crate::replay::PlayerInput;
+use crate::simulation::{Game, FIXED_DT};
+
+/// Шаг сценария: сколько секунд он длится и с какой скоростью поворачиваем змейку.
+pub type ScriptStep = (f32, f32);
+
+/// Раунд под управлением сценария.
+pub struct Scenario {
+    pub game: Game,
+}
+
+impl Scenario {
+    /// Одна змейка в центре пустого поля: без фруктов, препятствий, бомб и площадок.
+    /// Порталы закрыты.
+    pub fn new(config: GameConfig, seed: u64) -> Self {
+        let mut game = Game::new(config, PlayersMode::Single);
+        game.start_round(seed);
+        game.fruits.clear();
+        game.obstacles.clear();
+        game.bombs.clear();
+        game.speed_pads.clear();
+        game.portal = Portal::closed();
+        Self { game }
+    }
+
+    /// Кладём обычный фрукт в заданное место поля.
+    pub fn with_fruit(mut self, position: Vec2) -> Self {
+        self.game
+            .fruits
+            .push(Fruit::at(position, self.game.config.tuning.fruit_radius));
+        self
+    }
+
+    /// Прогоняем сценарий шагами `FIXED_DT`. Возвращаем, закончился ли раунд:
+    /// после его конца оставшиеся шаги сценария не выполняются.
+    pub fn run(&mut self, script: &[ScriptStep]) -> bool {
+        for &(duration, rotation) in script {
+            let input = PlayerInput {
+                rotation,
+                boost: false,
+            };
+            let steps = (duration / FIXED_DT).round() as usize;
+            for _ in 0..steps {
+                if self.game.step(&[input], FIXED_DT).ended {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::player::RunStats;
+    use crate::random::RNG_LOCK;
+    use crate::snake::DeathCause;
+
+    #[test]
+    fn straight_run_ends_at_wall() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut scenario = Scenario::new(GameConfig::default(), 1);
+        assert!(scenario.run(&[(30.0, 0.0)]));
+
+        let game = &scenario.game;
+        assert_eq!(game.players[0].death, Some(DeathCause::Wall));
+        assert!(game.players[0].snake.head.position().x > 0.0);
+    }
+
+    #[test]
+    fn fruit_ahead_is_eaten() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut scenario = Scenario::new(GameConfig::default(), 2).with_fruit(Vec2::new(0.5, 0.0));
+        assert!(!scenario.run(&[(1.0, 0.0)]));
+        assert_eq!(scenario.game.score(), 1);
+    }
+
+    #[test]
+    fn run_stats_count_fruit_distance_and_time() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut scenario = Scenario::new(GameConfig::default(), 4).with_fruit(Vec2::new(0.5, 0.0));
+        assert!(!scenario.run(&[(1.0, 0.0)]));
+
+        let game = &mut scenario.game;
+        let stats = game.players[0].stats;
+        assert_eq!(stats.fruits_eaten, 1);
+        assert_eq!(stats.max_length, 2);
+        assert!((stats.time - 1.0).abs() < 1e-3);
+        // За секунду голова проходит примерно столько, сколько показывает её скорость.
+        let speed = game.players[0].snake.head.speed;
+        assert!((stats.distance - speed).abs() < 0.1 * speed);
+
+        // Новый раунд начинается с пустыми итогами.
+        game.start_round(5);
+        assert_eq!(game.players[0].stats, RunStats::default());
+    }
+
+    #[test]
+    fn left_turn_leads_to_top_wall() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let config = GameConfig::default();
+        let rate = config.tuning.rotation_per_sec;
+        let mut scenario = Scenario::new(config, 3);
+        // Поворачиваем налево примерно на четверть оборота и едем прямо до стены.
+        let turn = std::f32::consts::FRAC_PI_2 / rate;
+        assert!(scenario.run(&[(turn, rate), (30.0, 0.0)]));
+
+        let game = &scenario.game;
+        assert_eq!(game.players[0].death, Some(DeathCause::Wall));
+        let head = game.players[0].snake.head.position();
+        assert!(head.y > head.x.abs());
+    }
+}
@@ -0,0 +1,600 @@
+//! Игровой мир без графики: змейки, фрукты, бонусы и всё, что с ними происходит за шаг.
+//! Здесь нет ни рисования, ни ввода, поэтому раунд можно прогонять и без окна.
+
+use macroquad::prelude::*;
+
+use crate::bomb::{spawn_bombs, Bomb};
+use crate::config::{Difficulty, GameConfig};
+use crate::fruit::{
+    occupied_positions, spawn_fruits, BonusFruit, Fruit, FruitKind, BONUS_FRUIT_CHANCE_PER_SEC,
+};
+use crate::obstacle::{level_for_score, obstacles_for_level, Obstacle, OBSTACLE_SAFE_DISTANCE};
+use crate::pad::{spawn_speed_pads, SpeedPad};
+use crate::particle::{spawn_burst, FloatingText, Particle};
+use crate::player::{restart_players, spawn_players, Control, Player, PlayersMode};
+use crate::portal::Portal;
+use crate::power_up::{PowerUp, PowerUpKind, POWER_UP_CHANCE_PER_SEC};
+use crate::random::rand_f32;
+use crate::replay::PlayerInput;
+use crate::snake::{DeathCause, Event};
+
+/// Длительность одного шага игры. Игра идёт целыми шагами независимо от частоты кадров,
+/// поэтому столкновения и поедание фруктов проверяются одинаково на любом компьютере.
+pub const FIXED_DT: f32 = 1.0 / 120.0;
+
+/// Больше стольких секунд за кадр не нагоняем. После долгой заминки шаги иначе
+/// не успевали бы за временем, и каждый следующий кадр получался бы ещё длиннее.
+/// Игровой цикл и так обрезает кадр до `MAX_FRAME_TIME`, поэтому в игре эта граница
+/// не срабатывает и остаётся на всякий случай.
+pub const MAX_CATCH_UP: f32 = 0.25;
+
+/// Ближе этого расстояния голова к стене или к своему телу - почти столкновение,
+/// и игра ненадолго замедляется.
+pub const NEAR_MISS_DISTANCE: f32 = 0.02;
+
+/// Во сколько раз замедляется игра после почти столкновения.
+pub const NEAR_MISS_TIME_SCALE: f32 = 0.3;
+
+/// Сколько секунд настоящего времени длится замедление.
+pub const NEAR_MISS_DURATION: f32 = 0.4;
+
+/// Сколько секунд после замедления оно не включается снова. Иначе змейка,
+/// ползущая вдоль стены, так и застряла бы в замедлении.
+pub const NEAR_MISS_COOLDOWN: f32 = 2.0;
+
+/// Сколько секунд после перехода на новый уровень показывается его номер.
+pub const LEVEL_BANNER_DURATION: f32 = 2.0;
+
+/// Копилка времени кадров для игры фиксированными шагами.
+#[derive(Default)]
+pub struct FixedStep {
+    pub accumulator: f32,
+}
+
+impl FixedStep {
+    /// Добавляем время кадра и возвращаем, сколько целых шагов пора сделать.
+    /// Остаток копится до следующего кадра.
+    pub fn advance(&mut self, frame_time: f32) -> usize {
+        self.accumulator = (self.accumulator + frame_time).min(MAX_CATCH_UP);
+        let steps = (self.accumulator / FIXED_DT) as usize;
+        self.accumulator -= steps as f32 * FIXED_DT;
+        steps
+    }
+}
+
+/// Короткое замедление игры, когда голова чуть не врезалась.
+#[derive(Default)]
+pub struct NearMiss {
+    /// Сколько секунд ещё длится замедление.
+    pub time_left: f32,
+    /// Сколько секунд ещё нельзя замедлиться снова.
+    pub cooldown: f32,
+}
+
+impl NearMiss {
+    /// Во сколько раз замедлить шаг длиной `dt`, если голова сейчас в опасности.
+    /// Время замедления и перерыва отсчитывается по настоящему `dt`, поэтому
+    /// замедление всегда заканчивается.
+    pub fn time_scale(&mut self, danger: bool, dt: f32) -> f32 {
+        if self.time_left <= 0.0 {
+            self.cooldown = (self.cooldown - dt).max(0.0);
+            if !danger || self.cooldown > 0.0 {
+                return 1.0;
+            }
+            self.time_left = NEAR_MISS_DURATION;
+            self.cooldown = NEAR_MISS_COOLDOWN;
+        }
+        self.time_left -= dt;
+        NEAR_MISS_TIME_SCALE
+    }
+}
+
+/// Что произошло за один шаг игры.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StepResult {
+    /// Сколько очков набрали все игроки за шаг.
+    pub score_delta: u32,
+    /// Съел ли кто-нибудь фрукт. По нему играется звук.
+    pub ate_fruit: bool,
+    /// Закончился ли раунд: кто-то проиграл, вышло время или змейка доросла до цели.
+    pub ended: bool,
+    /// Доросла ли змейка до цели в игре на длину.
+    pub won: bool,
+}
+
+/// Текущий раунд игры.
+pub struct Game {
+    pub config: GameConfig,
+    pub mode: PlayersMode,
+    pub players: Vec<Player>,
+    pub fruits: Vec<Fruit>,
+    pub obstacles: Vec<Obstacle>,
+    pub bombs: Vec<Bomb>,
+    /// Площадки на полу, меняющие скорость проезжающих змеек.
+    pub speed_pads: Vec<SpeedPad>,
+    pub portal: Portal,
+    pub power_up: Option<PowerUp>,
+    pub bonus: Option<BonusFruit>,
+    pub particles: Vec<Particle>,
+    /// Очки, всплывающие над съеденными фруктами.
+    pub floating_texts: Vec<FloatingText>,
+    /// Текущий уровень. Он растёт каждые `LEVEL_SCORE_STEP` очков лучшего игрока.
+    pub level: u32,
+    /// Сколько секунд ещё показывать номер нового уровня.
+    pub level_banner: f32,
+    pub near_miss: NearMiss,
+}
+
+impl Game {
+    /// Расставляем игроков, фрукты, препятствия и порталы по настройкам.
+    pub fn new(config: GameConfig, mode: PlayersMode) -> Self {
+        let players = spawn_players(mode, &config);
+        let fruits = spawn_fruits(&config, &players);
+        let obstacles = obstacles_for_level(0, &config);
+        let portal = Portal::respawn(&config, &obstacles);
+        let bombs = spawn_bombs(&config, &players, &fruits);
+        let speed_pads = spawn_speed_pads(&config, &obstacles);
+        Self {
+            config,
+            mode,
+            players,
+            fruits,
+            obstacles,
+            bombs,
+            speed_pads,
+            portal,
+            power_up: None,
+            bonus: None,
+            particles: Vec::new(),
+            floating_texts: Vec::new(),
+            level: 0,
+            level_banner: 0.0,
+            near_miss: NearMiss::default(),
+        }
+    }
+
+    /// Начинаем новый раунд: задаём зерно генератора и расставляем всё заново.
+    /// С одним и тем же зерном раунд начинается одинаково, на этом держатся записи раундов.
+    /// Время раунда отсчитывается заново, а константы берутся заново для выбранной сложности.
+    pub fn start_round(&mut self, seed: u64) {
+        rand::srand(seed);
+        self.config.round_time = 0.0;
+        self.config.reset_tuning();
+        self.level = 0;
+        self.level_banner = 0.0;
+        self.near_miss = NearMiss::default();
+        restart_players(&mut self.players, self.mode, &self.config);
+        self.respawn_field();
+        self.power_up = None;
+        self.bonus = None;
+        self.particles.clear();
+        self.floating_texts.clear();
+    }
+
+    /// Выбираем уровень сложности и расставляем всё заново под его размер поля.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.config.set_difficulty(difficulty);
+        self.respawn_players();
+        self.respawn_field();
+    }
+
+    /// Ставим змеек заново, например после смены режима игры.
+    pub fn respawn_players(&mut self) {
+        self.players = spawn_players(self.mode, &self.config);
+    }
+
+    /// Перекрашиваем змеек в цвета текущей темы, например после её смены.
+    pub fn recolor_snakes(&mut self) {
+        let colors = self.config.theme().snakes;
+        for (player, color) in self.players.iter_mut().zip(colors) {
+            player.snake.color = color;
+        }
+    }
+
+    /// Раскладываем фрукты, препятствия, порталы, бомбы и площадки заново,
+    /// например под новый размер поля.
+    pub fn respawn_field(&mut self) {
+        self.fruits = spawn_fruits(&self.config, &self.players);
+        self.obstacles = self.level_obstacles();
+        self.portal = Portal::respawn(&self.config, &self.obstacles);
+        self.bombs = spawn_bombs(&self.config, &self.players, &self.fruits);
+        self.speed_pads = spawn_speed_pads(&self.config, &self.obstacles);
+    }
+
+    /// Препятствия текущего уровня. Те, что оказались бы у самой головы какой-нибудь змейки,
+    /// не ставим, чтобы змейку не замуровало в новой стене.
+    fn level_obstacles(&self) -> Vec<Obstacle> {
+        obstacles_for_level(self.level, &self.config)
+            .into_iter()
+            .filter(|obstacle| {
+                self.players.iter().all(|player| {
+                    let head = player.snake.head.position();
+                    !obstacle.intersect(head, OBSTACLE_SAFE_DISTANCE / 2.0)
+                })
+            })
+            .collect()
+    }
+
+    /// Когда лучший счёт переходит очередной порог в `LEVEL_SCORE_STEP` очков,
+    /// препятствия и порталы расставляются заново под новый уровень.
+    fn update_level(&mut self, dt: f32) {
+        self.level_banner = (self.level_banner - dt).max(0.0);
+        let level = level_for_score(self.score());
+        if level <= self.level {
+            return;
+        }
+        self.level = level;
+        self.level_banner = LEVEL_BANNER_DURATION;
+        self.obstacles = self.level_obstacles();
+        self.portal = Portal::respawn(&self.config, &self.obstacles);
+    }
+
+    /// Лучший счёт среди игроков.
+    pub fn score(&self) -> u32 {
+        self.players.iter().map(|p| p.score).max().unwrap_or(0)
+    }
+
+    /// Продвигаем игру на `dt` секунд. Каждому игроку - его управление из `inputs`.
+    /// Если голова кого-нибудь из людей почти врезалась, то игра на этом шаге идёт медленнее.
+    /// Сам шаг от этого короче не становится, поэтому копилке шагов замедление не мешает.
+    pub fn step(&mut self, inputs: &[PlayerInput], dt: f32) -> StepResult {
+        let danger = self.players.iter().any(|p| {
+            !p.lost()
+                && !matches!(p.control, Control::Ai)
+                && p.snake.danger_distance(&self.config) < NEAR_MISS_DISTANCE
+        });
+        let dt = dt * self.near_miss.time_scale(danger, dt);
+
+        let Self {
+            config,
+            players,
+            fruits,
+            obstacles,
+            bombs,
+            speed_pads,
+            portal,
+            power_up,
+            bonus,
+            particles,
+            floating_texts,
+            ..
+        } = self;
+        let score_before: u32 = players.iter().map(|p| p.score).sum();
+        let mut ate_fruit = false;
+
+        // В режиме внезапной смерти стены со временем сдвигаются к центру.
+        // Фрукты, оказавшиеся за стеной, перекладываем внутрь поля.
+        // Так же перекладываем фрукты, которые слишком долго никто не ест.
+        // Подвижные фрукты сначала сдвигаются, отскакивая от стен.
+        // После этого магнит подтягивает фрукты к голове того, кто его подобрал.
+        config.advance_round(dt);
+        let half = config.half_extents();
+        let magnets: Vec<_> = players
+            .iter()
+            .filter(|p| p.has_effect(PowerUpKind::Magnet))
+            .map(|p| p.snake.head.position())
+            .collect();
+        for index in 0..fruits.len() {
+            let fruit = &mut fruits[index];
+            fruit.update(dt, config);
+            for &head in &magnets {
+                fruit.attract(head, dt, config);
+            }
+            let outside = fruit.position.x.abs() > half.x || fruit.position.y.abs() > half.y;
+            if fruit.grow_old(dt) || outside {
+                fruits[index] = Fruit::respawn(config, &occupied_positions(players), fruits);
+            }
+        }
+
+        // Перемещаем змеек и разбираем всё, что с ними случилось, в одном месте.
+        for (player_index, input) in inputs.iter().enumerate() {
+            let player = &mut players[player_index];
+            player.update_speed_pads(speed_pads, dt);
+            player.update_boost(input.boost, dt);
+            player.update_combo(dt);
+            if let Some(cause) = player.update_hunger(dt, config) {
+                player.death = Some(cause);
+            }
+            let rotation = player.steer(input.rotation);
+            let events = player.snake.update(dt, rotation, fruits, config);
+
+            // Голова, попавшая в портал, выходит из парного ему.
+            if portal.is_open(config) {
+                player.snake.enter_portal(portal, dt);
+            }
+
+            for event in events {
+                match event {
+                    // Начисляем очки и создаем новый фрукт вместо съеденного.
+                    // Змейка растёт на один сегмент за любой фрукт, независимо от его ценности,
+                    // кроме фиолетового: он укорачивает змейку, а очки остаются прежними.
+                    Event::AteFruit { index } => {
+                        let player = &mut players[player_index];
+                        let score = player.score;
+                        player.eat(&fruits[index], &config.tuning);
+                        let eaten = &fruits[index];
+                        spawn_burst(particles, eaten.position, eaten.kind.color(config.theme()));
+                        spawn_floating_text(floating_texts, eaten.position, player.score - score);
+                        if eaten.kind == FruitKind::Shrinking {
+                            player.snake.remove_unit();
+                        } else {
+                            player.snake.add_unit();
+                        }
+                        ate_fruit = true;
+                        // Новый фрукт кладём уже после роста змейки, чтобы он не оказался под ней.
+                        let occupied = occupied_positions(players);
+                        fruits[index] = Fruit::respawn(config, &occupied, fruits);
+                    }
+                    Event::Died(cause) => players[player_index].death = Some(cause),
+                }
+            }
+        }
+
+        // Изредка на поле ненадолго появляется ценный бонусный фрукт.
+        if bonus.is_none() && rand_f32() < BONUS_FRUIT_CHANCE_PER_SEC * dt {
+            let occupied = occupied_positions(players);
+            *bonus = Some(BonusFruit::respawn(config, &occupied, fruits));
+        }
+        for player in players.iter_mut() {
+            if let Some(eaten) = bonus.take_if(|b| player.snake.can_eat(&b.fruit)) {
+                let score = player.score;
+                player.eat(&eaten.fruit, &config.tuning);
+                let color = eaten.fruit.kind.color(config.theme());
+                spawn_burst(particles, eaten.fruit.position, color);
+                spawn_floating_text(floating_texts, eaten.fruit.position, player.score - score);
+                player.snake.add_unit();
+                ate_fruit = true;
+            }
+        }
+        // Несъеденный вовремя бонусный фрукт исчезает.
+        bonus.take_if(|b| !b.update(dt));
+
+        // Частицы разлетаются и исчезают, когда заканчивается их время жизни.
+        for particle in particles.iter_mut() {
+            particle.update(dt);
+        }
+        particles.retain(Particle::is_alive);
+        for text in floating_texts.iter_mut() {
+            text.update(dt);
+        }
+        floating_texts.retain(FloatingText::is_alive);
+
+        // Бомбы время от времени перебираются на новое место.
+        for bomb in bombs.iter_mut() {
+            if bomb.update(dt) {
+                bomb.relocate(config, players, fruits);
+            }
+        }
+
+        // Изредка выкладываем на поле бонус.
+        if power_up.is_none() && rand_f32() < POWER_UP_CHANCE_PER_SEC * dt {
+            *power_up = Some(PowerUp::respawn(config));
+        }
+
+        for player in players.iter_mut() {
+            if let Some(picked) = power_up.take_if(|p| player.snake.can_pick(p)) {
+                player.apply_power_up(picked.kind);
+            }
+            player.update_effect(dt);
+        }
+
+        // Кроме стен и себя, змейка проигрывает, если врезалась в препятствие, в бомбу
+        // или в другую змейку.
+        for index in 0..players.len() {
+            let snake = &players[index].snake;
+            let hits_other = players
+                .iter()
+                .enumerate()
+                .any(|(other, p)| other != index && snake.hits_snake(&p.snake));
+            let cause = if snake.hits_obstacle(obstacles) {
+                Some(DeathCause::Obstacle)
+            } else if snake.hits_bomb(bombs) {
+                Some(DeathCause::Bomb)
+            } else {
+                hits_other.then_some(DeathCause::Snake)
+            };
+            // Если змейка уже разбилась о стену или о себя, то причину не меняем.
+            let player = &mut players[index];
+            player.death = player.death.or(cause);
+        }
+        // Эффекты бонусов заканчиваются вместе с раундом проигравшего.
+        for player in players.iter_mut().filter(|p| p.lost()) {
+            player.clear_effect();
+        }
+        for player in players.iter_mut() {
+            player.stats.record(&player.snake, dt);
+        }
+
+        // Раунд заканчивается, как только проиграл хотя бы один игрок, вышло время
+        // или чья-нибудь змейка доросла до цели.
+        let time_is_up = config.time_left() == Some(0.0);
+        let won = config
+            .target_length()
+            .is_some_and(|target| players.iter().any(|p| p.snake.length() >= target));
+        let score_after: u32 = players.iter().map(|p| p.score).sum();
+        let result = StepResult {
+            score_delta: score_after - score_before,
+            ate_fruit,
+            ended: players.iter().any(Player::lost) || time_is_up || won,
+            won,
+        };
+        self.update_level(dt);
+        result
+    }
+}
+
+/// Показываем над съеденным фруктом, сколько очков он принёс. Фрукты без очков молчат.
+fn spawn_floating_text(texts: &mut Vec<FloatingText>, pos: Vec2, value: u32) {
+    if value > 0 {
+        texts.push(FloatingText::new(pos, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::TARGET_LENGTH;
+    use crate::obstacle::LEVEL_SCORE_STEP;
+    use crate::player::RunStats;
+    use crate::random::RNG_LOCK;
+
+    /// Раунд, в котором единственной змейкой управляет компьютер.
+    fn ai_game(seed: u64) -> Game {
+        let mut game = Game::new(GameConfig::default(), PlayersMode::Single);
+        game.start_round(seed);
+        game.players[0].control = Control::Ai;
+        game
+    }
+
+    fn play(game: &mut Game, steps: usize) -> u32 {
+        let mut score = 0;
+        for _ in 0..steps {
+            let player = &game.players[0];
+            let rotation = player.rotation(&game.fruits, &game.config);
+            let input = PlayerInput {
+                rotation,
+                boost: false,
+            };
+            let result = game.step(&[input], 1.0 / 60.0);
+            score += result.score_delta;
+            if result.ended {
+                break;
+            }
+        }
+        score
+    }
+
+    #[test]
+    fn fixed_step_keeps_remainder_and_clamps_stalls() {
+        let mut fixed_step = FixedStep::default();
+        assert_eq!(fixed_step.advance(FIXED_DT * 0.5), 0);
+        assert_eq!(fixed_step.advance(FIXED_DT * 2.0), 2);
+        assert!((fixed_step.accumulator - FIXED_DT * 0.5).abs() < 1e-6);
+
+        // После заминки в секунды нагоняем только ограниченное время.
+        let steps = fixed_step.advance(5.0);
+        assert_eq!(steps, (MAX_CATCH_UP / FIXED_DT) as usize);
+    }
+
+    #[test]
+    fn headless_round_is_deterministic() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut first = ai_game(11);
+        let first_score = play(&mut first, 3000);
+        let mut second = ai_game(11);
+        let second_score = play(&mut second, 3000);
+
+        assert!(first_score > 0);
+        assert_eq!(first_score, first.score());
+        assert_eq!(first_score, second_score);
+        assert_eq!(
+            first.players[0].snake.head.position(),
+            second.players[0].snake.head.position()
+        );
+    }
+
+    #[test]
+    fn round_ends_when_snake_hits_wall() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut game = Game::new(GameConfig::default(), PlayersMode::Single);
+        game.obstacles.clear();
+        // Порталы за стенами закрыты и не уводят змейку от стены.
+        game.portal = Portal {
+            a: Vec2::splat(10.0),
+            b: Vec2::splat(-10.0),
+        };
+        let input = PlayerInput {
+            rotation: 0.0,
+            boost: false,
+        };
+        let ended = (0..10_000).any(|_| game.step(&[input], 1.0 / 60.0).ended);
+        assert!(ended);
+        assert_eq!(game.players[0].death, Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn near_miss_slows_briefly_and_not_forever() {
+        let mut near_miss = NearMiss::default();
+        assert_eq!(near_miss.time_scale(false, FIXED_DT), 1.0);
+
+        // Даже если опасность не проходит, замедление кончается и долго не возвращается.
+        let steps = |seconds: f32| (seconds / FIXED_DT).round() as usize;
+        let slowed = (0..steps(10.0))
+            .filter(|_| near_miss.time_scale(true, FIXED_DT) < 1.0)
+            .count();
+        let cycles = 10.0 / (NEAR_MISS_DURATION + NEAR_MISS_COOLDOWN);
+        assert!(slowed <= steps(NEAR_MISS_DURATION * cycles.ceil()));
+        assert!(slowed >= steps(NEAR_MISS_DURATION));
+    }
+
+    #[test]
+    fn reaching_target_length_wins() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let config = GameConfig {
+            goal: true,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(config, PlayersMode::Single);
+        let input = PlayerInput {
+            rotation: 0.0,
+            boost: false,
+        };
+        assert!(!game.step(&[input], FIXED_DT).won);
+
+        while game.players[0].snake.length() < TARGET_LENGTH {
+            game.players[0].snake.add_unit();
+        }
+        let result = game.step(&[input], FIXED_DT);
+        assert!(result.won && result.ended);
+        assert!(!game.players[0].lost());
+    }
+
+    #[test]
+    fn level_up_rebuilds_obstacles_around_head() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut game = Game::new(GameConfig::default(), PlayersMode::Single);
+        // Голова стоит там, где в узоре первого уровня препятствие.
+        let head = obstacles_for_level(1, &game.config)[0].position;
+        game.players[0].snake.move_to(head);
+        game.players[0].score = LEVEL_SCORE_STEP;
+        let input = PlayerInput {
+            rotation: 0.0,
+            boost: false,
+        };
+        game.step(&[input], FIXED_DT);
+
+        assert_eq!(game.level, 1);
+        assert!(game.level_banner > 0.0);
+        assert!(game.obstacles.len() < obstacles_for_level(1, &game.config).len());
+        assert!(!game.players[0].snake.hits_obstacle(&game.obstacles));
+    }
+
+    #[test]
+    fn new_round_restores_snakes_in_place() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut game = ai_game(6);
+        game.players[0].score = 7;
+        for _ in 0..5 {
+            game.players[0].snake.add_unit();
+        }
+        play(&mut game, 120);
+        let capacity = game.players[0].snake.units.capacity();
+        assert!(capacity > 0);
+        let cells = game.players[0].snake.grid.cells.as_ptr();
+        let trail = game.players[0].snake.trail.capacity();
+
+        game.start_round(8);
+        let mut fresh = Game::new(GameConfig::default(), PlayersMode::Single);
+        fresh.start_round(8);
+        let player = &game.players[0];
+        assert_eq!(player.snake, fresh.players[0].snake);
+        assert_eq!(player.score, 0);
+        // Память змейки прежняя: ни сегменты, ни сетка, ни след не выделялись заново.
+        assert_eq!(player.snake.units.capacity(), capacity);
+        assert_eq!(player.snake.grid.cells.as_ptr(), cells);
+        assert_eq!(player.snake.trail.capacity(), trail);
+        assert_eq!(player.stats, RunStats::default());
+        assert!(matches!(player.control, Control::Keyboard(_)));
+    }
+}
@@ -0,0 +1,1496 @@
+//! Змейка: сегменты, голова и сетка для быстрого поиска столкновений.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+
+use crate::bomb::Bomb;
+use crate::config::{Config, GameConfig};
+use crate::fruit::Fruit;
+use crate::lang::Strings;
+use crate::obstacle::Obstacle;
+use crate::portal::{Portal, PORTAL_RADIUS};
+use crate::power_up::{PowerUp, POWER_UP_RADIUS};
+use crate::render::ScreenTransform;
+use crate::save;
+
+/// Начальная скорость змеи по умолчанию.
+pub const INIT_SPEED: f32 = 0.4;
+
+/// Насколько змея ускоряется с каждым новым сегментом.
+pub const SPEED_PER_UNIT: f32 = 0.01;
+
+/// Наибольшая скорость змеи, чтобы длинной змеёй ещё можно было управлять.
+pub const MAX_SPEED: f32 = 0.9;
+
+/// Радиус элемента змеи по умолчанию.
+pub const UNIT_RADIUS: f32 = 0.04;
+
+/// Скорасть вращения змейки по умолчанию (радианы в секунду).
+pub const ROTATION_PER_SEC: f32 = 2.0;
+
+/// Сколько секунд голова раздувается после того, как съела фрукт.
+pub const EAT_ANIM_DURATION: f32 = 0.25;
+
+/// На какую долю радиуса раздувается голова сразу после еды.
+pub const EAT_POP_SCALE: f32 = 0.3;
+
+/// С какого расстояния до фрукта голова начинает открывать рот.
+pub const MOUTH_OPEN_DISTANCE: f32 = 0.25;
+
+/// Половина угла полностью открытого рта. Меньше угла между глазами, чтобы рот их не закрывал.
+pub const MOUTH_MAX_ANGLE: f32 = 0.25;
+
+/// Как быстро змейка набирает и сбрасывает скорость поворота (радианы в секунду за секунду).
+pub const ANGULAR_ACCELERATION: f32 = 15.0;
+
+/// Сцепление головы с полем по умолчанию: какую долю разницы между движением головы
+/// и её направлением она догоняет за секунду. Такое большое сцепление догоняет сразу,
+/// и голова движется точно туда, куда смотрит. С маленьким она скользит на поворотах, как по льду.
+pub const HEAD_GRIP: f32 = 1000.0;
+
+/// Сколько первых сегментов за головой по умолчанию не считаются столкновением.
+/// Первый сегмент всегда касается головы.
+pub const SELF_COLLISION_GRACE: usize = 1;
+
+/// Голова врезается в свой сегмент, когда их центры ближе стольких радиусов.
+/// Чуть меньше двух радиусов, чтобы соседние на повороте сегменты не считались столкновением.
+pub const SELF_HIT_SCALE: f32 = 1.8;
+
+/// Переносим точку на противоположную сторону поля, если она вышла за его край.
+/// Для разности двух точек это даёт кратчайший путь между ними через края поля.
+/// `extents` - ширина и высота поля.
+pub fn wrap_position(pos: Vec2, extents: Vec2) -> Vec2 {
+    let wrap = |coord: f32, size: f32| (coord + size / 2.0).rem_euclid(size) - size / 2.0;
+    Vec2::new(wrap(pos.x, extents.x), wrap(pos.y, extents.y))
+}
+
+/// Элемент змейки
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Unit {
+    #[serde(with = "save::vec2")]
+    pub position: Vec2,
+}
+
+impl Unit {
+    /// Элемент будет перемещаться в сторону предыдущего элемента змейки, если тот отдаляется.
+    pub fn go(&mut self, prev_unit_pos: Vec2, radius: f32) {
+        let to_prev = prev_unit_pos - self.position;
+        let distance = to_prev.length();
+        let shift = distance - 2.0 * radius;
+
+        // Если расстояние до следующего элемента больше двух радиусов,
+        // то смещаемся, чтобы змейка не разрывалась.
+        if shift > 0.0 {
+            self.position += to_prev.normalize() * shift;
+        }
+    }
+
+    /// Отображение элемента змейки в виде круга заданного цвета и радиуса.
+    /// В пятнистой раскраске поверх круга рисуем тёмное пятнышко.
+    pub fn draw(&self, color: Color, radius: f32, config: &GameConfig, screen: &ScreenTransform) {
+        let radius_pixels = radius * screen.pixels_per_meter;
+        let screen_pos = screen.to_screen(self.position);
+        draw_circle(screen_pos.x, screen_pos.y, radius_pixels, color);
+        if config.skin == Skin::Spotted {
+            let spot_color = darken(color, SPOT_BRIGHTNESS);
+            draw_circle(
+                screen_pos.x,
+                screen_pos.y,
+                radius_pixels * SPOT_SIZE,
+                spot_color,
+            );
+        }
+    }
+
+    /// Проверка пересечения сегмента змейки с точкой: ближе ли она, чем на `distance`.
+    /// Для пересечения двух кругов это сумма их радиусов.
+    pub fn intersect(&self, position: Vec2, distance: f32) -> bool {
+        self.position.distance(position) < distance
+    }
+}
+
+/// Голова змейки.
+/// Это особый элемент змейки, который вращается и двигается согласно действиям пользователя.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Head {
+    pub unit: Unit,
+    #[serde(with = "save::vec2")]
+    pub direction: Vec2,
+    pub speed: f32,
+    /// Множитель скорости от действующего бонуса.
+    pub speed_multiplier: f32,
+    /// Текущая скорость поворота (радианы в секунду).
+    #[serde(default)]
+    pub angular_velocity: f32,
+    /// Скорость, с которой голова на самом деле движется. Она догоняет скорость
+    /// по направлению головы не сразу, поэтому на крутых поворотах голову заносит.
+    #[serde(default, with = "save::vec2")]
+    pub velocity: Vec2,
+    /// Сколько ещё длится раздувание головы после еды: от 1 сразу после еды до 0.
+    #[serde(skip)]
+    pub eat_anim: f32,
+    /// Насколько открыт рот: от 0 до 1, когда фрукт у самой головы.
+    #[serde(skip)]
+    pub mouth_open: f32,
+}
+
+impl Head {
+    /// Голова в центре поля, которая смотрит вправо и движется со скоростью `speed`.
+    pub fn new(speed: f32) -> Self {
+        Self {
+            unit: Unit {
+                position: Vec2::ZERO,
+            },
+            direction: Vec2::X,
+            speed,
+            speed_multiplier: 1.0,
+            angular_velocity: 0.0,
+            velocity: Vec2::X * speed,
+            eat_anim: 0.0,
+            mouth_open: 0.0,
+        }
+    }
+
+    /// Скорость поворота плавно тянется к желаемой, а отпущенная клавиша плавно её гасит.
+    /// Быстрее `max_rate` голова не поворачивает.
+    pub fn steer(&mut self, target: f32, max_rate: f32, dt: f32) {
+        let max_change = ANGULAR_ACCELERATION * dt;
+        let change = (target - self.angular_velocity).clamp(-max_change, max_change);
+        self.angular_velocity = (self.angular_velocity + change).clamp(-max_rate, max_rate);
+    }
+
+    /// Вращение головы змейки с текущей скоростью поворота.
+    pub fn rotate(&mut self, dt: f32) {
+        let rotation = Vec2::from_angle(self.angular_velocity * dt);
+        let new_head_direction = rotation.rotate(self.direction);
+        self.direction = new_head_direction;
+    }
+
+    /// Перемещение головы змейки. Скорость движения тянется к скорости по направлению
+    /// головы тем быстрее, чем больше сцепление `grip`.
+    pub fn go(&mut self, dt: f32, grip: f32) {
+        let target = self.speed * self.speed_multiplier * self.direction;
+        self.velocity = self.velocity.lerp(target, (grip * dt).min(1.0));
+        self.unit.position += self.velocity * dt;
+    }
+
+    /// Возвращаем позицию головы.
+    pub fn position(&self) -> Vec2 {
+        self.unit.position
+    }
+
+    /// Голова съела фрукт и ненадолго раздувается.
+    pub fn bite(&mut self) {
+        self.eat_anim = 1.0;
+    }
+
+    /// Анимация головы: раздувание после еды затухает, а рот открывается,
+    /// когда до ближайшего фрукта остаётся `fruit_distance`.
+    pub fn animate(&mut self, dt: f32, fruit_distance: Option<f32>) {
+        self.eat_anim = (self.eat_anim - dt / EAT_ANIM_DURATION).max(0.0);
+        self.mouth_open = fruit_distance.map_or(0.0, |distance| {
+            (1.0 - distance / MOUTH_OPEN_DISTANCE).clamp(0.0, 1.0)
+        });
+    }
+
+    /// Отображаем голову змейки.
+    /// Если есть текстура головы, то рисуем её, повернув по направлению движения.
+    /// Текстура должна смотреть вправо, а окрашивается она в цвет змейки.
+    pub fn draw(
+        &self,
+        color: Color,
+        radius: f32,
+        skin: Option<&Texture2D>,
+        config: &GameConfig,
+        screen: &ScreenTransform,
+    ) {
+        let radius = radius * (1.0 + EAT_POP_SCALE * self.eat_anim);
+        if let Some(texture) = skin {
+            let size = 2.0 * radius * screen.pixels_per_meter;
+            let screen_pos = screen.to_screen(self.position());
+            // На экране ось Y направлена вниз, поэтому угол меняет знак.
+            let angle = -self.direction.y.atan2(self.direction.x);
+            let params = DrawTextureParams {
+                dest_size: Some(Vec2::splat(size)),
+                rotation: angle,
+                ..Default::default()
+            };
+            let corner = screen_pos - Vec2::splat(size / 2.0);
+            draw_texture_ex(texture, corner.x, corner.y, color, params);
+            return;
+        }
+
+        self.unit.draw(color, radius, config, screen);
+
+        // Рот открывается клином от центра головы, когда рядом фрукт.
+        if self.mouth_open > 0.0 {
+            let angle = MOUTH_MAX_ANGLE * self.mouth_open;
+            let corner = |angle| {
+                let shift = Vec2::from_angle(angle).rotate(self.direction) * radius;
+                screen.to_screen(self.position() + shift)
+            };
+            let center = screen.to_screen(self.position());
+            draw_triangle(center, corner(angle), corner(-angle), BLACK);
+        }
+
+        // Помимо отображения обычного сегмента, отобразим глаза по направлению движекния.
+        let angle = 0.3; // Половина угла между глазами.
+        let left_eye_shift = Vec2::from_angle(angle).rotate(self.direction) * radius;
+        let left_eye_pos = screen.to_screen(self.position() + left_eye_shift);
+        let right_eye_shift = Vec2::from_angle(-angle).rotate(self.direction) * radius;
+        let right_eye_pos = screen.to_screen(self.position() + right_eye_shift);
+        let eye_r = radius / 6.0 * screen.pixels_per_meter;
+
+        draw_circle(left_eye_pos.x, left_eye_pos.y, eye_r, BLACK);
+        draw_circle(right_eye_pos.x, right_eye_pos.y, eye_r, BLACK);
+
+        // Перед головой рисуем стрелку, которая показывает направление движения.
+        if config.direction_arrow {
+            let point = |forward: f32, side: f32| {
+                let shift = self.direction.rotate(Vec2::new(forward, side)) * radius;
+                screen.to_screen(self.position() + shift)
+            };
+            draw_triangle(point(1.7, 0.0), point(1.15, 0.4), point(1.15, -0.4), color);
+        }
+    }
+
+    /// Проверка пересечения головы змейки с точкой: ближе ли она, чем на `distance`.
+    pub fn intersect(&self, position: Vec2, distance: f32) -> bool {
+        self.unit.intersect(position, distance)
+    }
+}
+
+/// Равномерная сетка над игровым полем.
+/// В каждой ячейке храним индексы сегментов змейки, которые в неё попали.
+/// Сетка квадратная: `field_size` - большая сторона поля.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpatialGrid {
+    pub field_size: f32,
+    /// Размер ячейки. Он не меньше расстояния, на котором голова пересекается с сегментом,
+    /// поэтому достаточно проверять только соседние с головой ячейки.
+    pub cell_size: f32,
+    pub cells_per_side: usize,
+    pub cells: Vec<Vec<usize>>,
+}
+
+impl SpatialGrid {
+    /// Пустая сетка, покрывающая всё игровое поле, для сегментов заданного радиуса.
+    pub fn new(field_size: f32, unit_radius: f32) -> Self {
+        let cell_size = 2.0 * unit_radius;
+        let cells_per_side = (field_size / cell_size).ceil() as usize;
+        Self {
+            field_size,
+            cell_size,
+            cells_per_side,
+            cells: vec![Vec::new(); cells_per_side * cells_per_side],
+        }
+    }
+
+    /// Координаты ячейки, в которую попадает точка.
+    /// Точки за пределами поля относим к крайним ячейкам.
+    fn cell_of(&self, position: Vec2) -> (usize, usize) {
+        let max_cell = self.cells_per_side as i32 - 1;
+        let cell = ((position + self.field_size / 2.0) / self.cell_size).floor();
+        let x = (cell.x as i32).clamp(0, max_cell);
+        let y = (cell.y as i32).clamp(0, max_cell);
+        (x as usize, y as usize)
+    }
+
+    /// Заново раскладываем сегменты по ячейкам.
+    /// Если размер поля изменился, то сетка перестраивается под новый размер.
+    pub fn rebuild(&mut self, units: &[Unit], field_size: f32) {
+        if field_size != self.field_size {
+            *self = Self::new(field_size, self.cell_size / 2.0);
+        }
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+        for (index, unit) in units.iter().enumerate() {
+            let (x, y) = self.cell_of(unit.position);
+            self.cells[y * self.cells_per_side + x].push(index);
+        }
+    }
+
+    /// Индексы сегментов из ячейки точки и соседних с ней ячеек.
+    pub fn neighbours(&self, position: Vec2) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = self.cell_of(position);
+        let max_cell = self.cells_per_side - 1;
+        let xs = x.saturating_sub(1)..=(x + 1).min(max_cell);
+        let ys = y.saturating_sub(1)..=(y + 1).min(max_cell);
+        ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+            .flat_map(move |(x, y)| self.cells[y * self.cells_per_side + x].iter().copied())
+    }
+}
+
+/// Пустая сетка для поля и сегментов размеров по умолчанию.
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        let tuning = Config::default();
+        Self::new(tuning.field_size, tuning.unit_radius)
+    }
+}
+
+/// Сколько последних позиций головы хранится для следа.
+pub const TRAIL_LENGTH: usize = 10;
+
+/// Непрозрачность самой свежей точки следа.
+pub const TRAIL_ALPHA: f32 = 0.4;
+
+/// Сколько раз в секунду мигает неуязвимая змейка.
+pub const INVINCIBLE_BLINK_RATE: f64 = 8.0;
+
+/// Больше этой доли радиуса голова за один короткий шаг не проходит.
+pub const MAX_SUBSTEP_SHARE: f32 = 0.5;
+
+/// За сколько секунд новый сегмент расходится с предыдущим на полное расстояние.
+pub const GROWTH_DURATION: f32 = 0.3;
+
+/// Сколько ближайших к голове сегментов не считаем опасными при проверке сближения:
+/// так круто голова повернуть не успевает.
+pub const DANGER_IGNORED_UNITS: usize = 4;
+
+/// Непрозрачность неуязвимой змейки в погасшей фазе мигания.
+pub const INVINCIBLE_BLINK_ALPHA: f32 = 0.35;
+
+/// Яркость кончика хвоста относительно цвета головы.
+pub const TAIL_BRIGHTNESS: f32 = 0.45;
+
+/// Цвет сегмента с индексом `index` в теле из `len` сегментов: от цвета головы
+/// к более тёмному цвету хвоста. Последний сегмент получает цвет хвоста,
+/// а голова, которой в теле нет, всегда остаётся чуть ярче первого сегмента.
+pub fn body_color(head: Color, index: usize, len: usize) -> Color {
+    let tail = darken(head, TAIL_BRIGHTNESS);
+    let share = (index + 1) as f32 / len.max(1) as f32;
+    Color::from_vec(head.to_vec().lerp(tail.to_vec(), share.min(1.0)))
+}
+
+/// Яркость тёмных полосок полосатой раскраски относительно цвета сегмента.
+pub const STRIPE_BRIGHTNESS: f32 = 0.7;
+
+/// Размер пятнышка пятнистой раскраски относительно радиуса сегмента.
+pub const SPOT_SIZE: f32 = 0.45;
+
+/// Яркость пятнышка относительно цвета сегмента.
+pub const SPOT_BRIGHTNESS: f32 = 0.6;
+
+/// Раскраска змейки. Меняет только то, как змейка нарисована: размер сегментов
+/// и столкновения от неё не зависят.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Skin {
+    /// Сплошной цвет.
+    Solid,
+    /// Сегменты через один темнее.
+    Striped,
+    /// В каждом сегменте тёмное пятнышко.
+    Spotted,
+}
+
+impl Skin {
+    /// Все раскраски в порядке объявления.
+    pub const ALL: [Self; 3] = [Self::Solid, Self::Striped, Self::Spotted];
+
+    /// Название раскраски для меню.
+    pub fn name(self, strings: &Strings) -> &'static str {
+        strings.skins[self as usize]
+    }
+
+    /// Следующая раскраска по кругу.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// Предыдущая раскраска по кругу.
+    pub fn prev(self) -> Self {
+        Self::ALL[(self as usize + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Цвет сегмента с индексом `index`: в полосатой раскраске нечётные сегменты темнее.
+    pub fn segment_color(self, color: Color, index: usize) -> Color {
+        if self == Self::Striped && index % 2 == 1 {
+            darken(color, STRIPE_BRIGHTNESS)
+        } else {
+            color
+        }
+    }
+}
+
+/// Цвет той же прозрачности, но с яркостью `brightness` от исходной.
+fn darken(color: Color, brightness: f32) -> Color {
+    Color {
+        r: color.r * brightness,
+        g: color.g * brightness,
+        b: color.b * brightness,
+        a: color.a,
+    }
+}
+
+/// Почему змейка проиграла.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeathCause {
+    /// Змейка разбилась о стену.
+    Wall,
+    /// Змейка врезалась в себя.
+    Self_,
+    /// Змейка врезалась в препятствие.
+    Obstacle,
+    /// Змейка наехала на бомбу.
+    Bomb,
+    /// Змейка врезалась в другую змейку.
+    Snake,
+    /// Змейка из одной головы слишком долго не ела.
+    Starved,
+}
+
+impl DeathCause {
+    /// Что показываем игроку на экране конца игры.
+    pub fn message(self, strings: &Strings) -> &'static str {
+        strings.deaths[self as usize]
+    }
+}
+
+/// Что случилось со змейкой за один шаг.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// Голова дотянулась до фрукта с заданным индексом.
+    AteFruit { index: usize },
+    /// Змейка разбилась о стену или врезалась в себя.
+    Died(DeathCause),
+}
+
+/// Змейка - это голова и сегменты.
+/// Сетку и след не сохраняем: сетку можно разложить заново, а след быстро нарастает сам.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snake {
+    pub head: Head,
+    pub units: Vec<Unit>,
+    /// Сетка с сегментами змейки, чтобы не проверять пересечение головы с каждым из них.
+    #[serde(skip)]
+    pub grid: SpatialGrid,
+    /// Цвет змейки, чтобы различать змеек разных игроков.
+    #[serde(with = "save::color")]
+    pub color: Color,
+    /// Последние позиции головы, от старых к новым. По ним рисуется след.
+    #[serde(skip)]
+    pub trail: VecDeque<Vec2>,
+    /// Радиус головы и сегментов.
+    pub radius: f32,
+    /// Скорость змейки без сегментов.
+    pub init_speed: f32,
+    /// Сколько первых сегментов за головой голова может задевать, не проигрывая.
+    pub self_collision_grace: usize,
+    /// Порталы, в которые входила голова. Сегменты тянутся к предыдущим через них.
+    pub portal: Option<Portal>,
+    /// Сколько секунд змейка ещё не может снова войти в портал.
+    pub portal_cooldown: f32,
+    /// Голова проходит сквозь собственное тело. Включается бонусом игрока, поэтому не сохраняется.
+    #[serde(skip)]
+    pub invincible: bool,
+    /// Сколько съеденных сегментов ещё не выросло. Они вырастают по одному у хвоста.
+    #[serde(default)]
+    pub pending_growth: usize,
+    /// Насколько последний сегмент ещё не разошёлся с предыдущим: от 1 сразу после
+    /// появления до 0, когда между ними обычное расстояние.
+    #[serde(default)]
+    pub tail_growth: f32,
+    /// Сколько голова прошла к следующей клетке в классическом режиме.
+    #[serde(default)]
+    pub cell_progress: f32,
+    /// Куда было нажато управление на прошлом шаге классического режима: 1, -1 или 0.
+    #[serde(default)]
+    pub turn_held: f32,
+    /// Поворот, который классическая змейка сделает на следующем ходе: 1, -1 или 0.
+    #[serde(default)]
+    pub queued_turn: f32,
+}
+
+impl Snake {
+    /// Перемещение змейки - это вращение и перемещение головы и, затем, последовательное перемещение всех сегментов.
+    pub fn go(&mut self, dt: f32, rotation: f32, config: &GameConfig) {
+        let extents = config.field_extents();
+        if self.trail.len() == TRAIL_LENGTH {
+            self.trail.pop_front();
+        }
+        self.trail.push_back(self.head.position());
+
+        self.head
+            .steer(rotation, config.tuning.rotation_per_sec, dt);
+        self.head.rotate(dt);
+        self.head.go(dt, config.tuning.head_grip);
+        if config.walls_wrap() {
+            self.head.unit.position = wrap_position(self.head.unit.position, extents);
+        }
+
+        self.grow(dt);
+
+        let mut prev_unit_pos = self.head.position();
+        let tail = self.units.len().saturating_sub(1);
+        for (index, unit) in self.units.iter_mut().enumerate() {
+            // Предыдущий сегмент мог только что перейти на другую сторону поля.
+            // Тогда тянемся к нему через край поля, а не через всё поле.
+            let mut to_prev = if config.walls_wrap() {
+                wrap_position(prev_unit_pos - unit.position, extents)
+            } else {
+                prev_unit_pos - unit.position
+            };
+            // Так же и через портал: тянемся к его входу, а дойдя до входа, переходим к выходу.
+            let mut jump = None;
+            for (entry, shift) in self.portal.iter().flat_map(Portal::jumps) {
+                let through_portal = prev_unit_pos - shift - unit.position;
+                if through_portal.length() < to_prev.length() {
+                    to_prev = through_portal;
+                    jump = Some((entry, shift));
+                }
+            }
+
+            // Только что выросший хвост отходит от предыдущего сегмента постепенно:
+            // он держится на расстоянии, как если бы был меньше.
+            let spread = if index == tail {
+                1.0 - self.tail_growth
+            } else {
+                1.0
+            };
+            unit.go(unit.position + to_prev, self.radius * spread);
+            if config.walls_wrap() {
+                unit.position = wrap_position(unit.position, extents);
+            }
+            if let Some((entry, shift)) = jump {
+                if unit.position.distance(entry) < PORTAL_RADIUS {
+                    unit.position += shift;
+                }
+            }
+            prev_unit_pos = unit.position;
+        }
+        self.grid.rebuild(&self.units, extents.max_element());
+    }
+
+    /// Перемещаем змейку и сообщаем, что с ней после этого случилось.
+    /// Столкновения с препятствиями и другими змейками проверяются отдельно.
+    /// Длинный шаг делим на короткие, чтобы голова не проскочила фрукт или стену.
+    /// Съеденный фрукт шаг не прерывает: змейка проходит всё своё расстояние, а каждый
+    /// фрукт попадает в события один раз. Останавливаемся, только если змейка разбилась.
+    pub fn update(
+        &mut self,
+        dt: f32,
+        rotation: f32,
+        fruits: &[Fruit],
+        config: &GameConfig,
+    ) -> Vec<Event> {
+        let distance = self.head.speed * self.head.speed_multiplier * dt;
+        let substeps = (distance / (self.radius * MAX_SUBSTEP_SHARE))
+            .ceil()
+            .max(1.0);
+        let substep_dt = dt / substeps;
+
+        let mut events = Vec::new();
+        for _ in 0..substeps as usize {
+            if config.classic {
+                self.go_classic(substep_dt, rotation, config);
+            } else {
+                self.go(substep_dt, rotation, config);
+            }
+            // Съеденный фрукт убирают после шага, поэтому до конца шага он ещё лежит на месте.
+            if let Some(index) = self.eatable_fruit(fruits) {
+                let event = Event::AteFruit { index };
+                if !events.contains(&event) {
+                    events.push(event);
+                }
+            }
+            if let Some(cause) = self.death_cause(config) {
+                events.push(Event::Died(cause));
+                break;
+            }
+        }
+
+        let head = self.head.position();
+        let fruit_distance = self
+            .nearest_fruit(fruits)
+            .map(|fruit| head.distance(fruit.position) - fruit.radius);
+        self.head.animate(dt, fruit_distance);
+        events
+    }
+
+    /// Отображение змейки. Голову можно нарисовать текстурой `head_skin`.
+    pub fn draw(
+        &self,
+        config: &GameConfig,
+        screen: &ScreenTransform,
+        head_skin: Option<&Texture2D>,
+    ) {
+        // Неуязвимая змейка мигает, то становясь полупрозрачной, то снова обычной.
+        let mut color = self.color;
+        if self.invincible && (get_time() * INVINCIBLE_BLINK_RATE) as u64 % 2 == 1 {
+            color.a *= INVINCIBLE_BLINK_ALPHA;
+        }
+
+        // След рисуем под головой: чем старше точка, тем она меньше и прозрачнее.
+        let ppm = screen.pixels_per_meter;
+        for (age, &position) in self.trail.iter().rev().enumerate() {
+            let freshness = 1.0 - (age + 1) as f32 / (TRAIL_LENGTH + 1) as f32;
+            let mut trail_color = color;
+            trail_color.a *= TRAIL_ALPHA * freshness;
+            let screen_pos = screen.to_screen(position);
+            draw_circle(
+                screen_pos.x,
+                screen_pos.y,
+                self.radius * freshness * ppm,
+                trail_color,
+            );
+        }
+
+        // Соединяем центры соседних сегментов толстыми линиями, чтобы тело было сплошным
+        // даже на крутых поворотах. Сегменты, разнесённые по разным краям поля, не соединяем.
+        // Линия красится в цвет сегмента, к которому она идёт от головы.
+        let skin = config.skin;
+        let thickness = 2.0 * self.radius * ppm;
+        let len = self.units.len();
+        let pairs = self.segments().zip(self.segments().skip(1));
+        for (index, (from, to)) in pairs.enumerate() {
+            if from.distance(to) > 4.0 * self.radius {
+                continue;
+            }
+            let from = screen.to_screen(from);
+            let to = screen.to_screen(to);
+            draw_line(
+                from.x,
+                from.y,
+                to.x,
+                to.y,
+                thickness,
+                skin.segment_color(body_color(color, index, len), index),
+            );
+        }
+
+        // Поверх соединений рисуем круглые сегменты, а голову последней, чтобы были видны глаза.
+        for (index, unit) in self.units.iter().enumerate() {
+            let unit_color = skin.segment_color(body_color(color, index, len), index);
+            unit.draw(unit_color, self.radius, config, screen);
+        }
+        self.head
+            .draw(color, self.radius, head_skin, config, screen);
+    }
+
+    /// Длина змейки вместе с ещё не выросшими сегментами.
+    pub fn length(&self) -> u32 {
+        (self.units.len() + self.pending_growth + 1) as _
+    }
+
+    /// Позиции головы и всех сегментов змейки по порядку, от головы к хвосту.
+    /// Так змейку удобно читать снаружи, не заглядывая в её поля.
+    pub fn segments(&self) -> impl Iterator<Item = Vec2> + '_ {
+        std::iter::once(self.head.position()).chain(self.units.iter().map(|unit| unit.position))
+    }
+
+    /// Если голова змейки пересикается с фруктом, то она может его съесть.
+    pub fn can_eat(&self, fruit: &Fruit) -> bool {
+        self.head
+            .intersect(fruit.position, fruit.radius + self.radius)
+    }
+
+    /// Индекс фрукта, который змейка может съесть, если такой есть.
+    pub fn eatable_fruit(&self, fruits: &[Fruit]) -> Option<usize> {
+        fruits.iter().position(|fruit| self.can_eat(fruit))
+    }
+
+    /// Если голова змейки пересекается с бонусом, то она может его подобрать.
+    pub fn can_pick(&self, power_up: &PowerUp) -> bool {
+        self.head
+            .intersect(power_up.position, POWER_UP_RADIUS + self.radius)
+    }
+
+    /// Врезалась ли голова змейки в одно из препятствий.
+    pub fn hits_obstacle(&self, obstacles: &[Obstacle]) -> bool {
+        obstacles
+            .iter()
+            .any(|obstacle| obstacle.intersect(self.head.position(), self.radius))
+    }
+
+    /// Наехала ли голова змейки на бомбу.
+    pub fn hits_bomb(&self, bombs: &[Bomb]) -> bool {
+        bombs
+            .iter()
+            .any(|bomb| bomb.intersect(self.head.position(), self.radius))
+    }
+
+    /// Врезалась ли голова змейки в другую змейку.
+    pub fn hits_snake(&self, other: &Snake) -> bool {
+        let distance = self.radius + other.radius;
+        self.head.intersect(other.head.position(), distance)
+            || other
+                .units
+                .iter()
+                .any(|u| self.head.intersect(u.position, distance))
+    }
+
+    /// Добавляем новый сегмент к змейке. Сегмент вырастает у хвоста не сразу,
+    /// а когда предыдущий выросший сегмент разойдётся с хвостом. Быстрее змейка становится сразу.
+    pub fn add_unit(&mut self) {
+        self.pending_growth += 1;
+        self.update_speed();
+    }
+
+    /// Выращиваем у хвоста следующий съеденный сегмент, когда предыдущий уже разошёлся.
+    /// Новый сегмент появляется на месте последнего, а расходятся они в `go`.
+    fn grow(&mut self, dt: f32) {
+        self.tail_growth = (self.tail_growth - dt / GROWTH_DURATION).max(0.0);
+        if self.tail_growth > 0.0 || self.pending_growth == 0 {
+            return;
+        }
+        self.pending_growth -= 1;
+        // В качестве позиции используем позицию последнего сегмента.
+        // Или головы, если сегментов нет.
+        let last_unit = self.units.last().unwrap_or(&self.head.unit);
+        self.units.push(*last_unit);
+        self.tail_growth = 1.0;
+    }
+
+    /// Убираем последний сегмент змейки. Сначала отменяется ещё не выросший сегмент.
+    /// Голову убрать нельзя, поэтому у змейки из одной головы ничего не меняется.
+    pub fn remove_unit(&mut self) {
+        if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+        } else if self.units.pop().is_some() {
+            self.tail_growth = 0.0;
+            let field_size = self.grid.field_size;
+            self.grid.rebuild(&self.units, field_size);
+        } else {
+            return;
+        }
+        self.update_speed();
+    }
+
+    /// Чем длиннее змейка, тем она быстрее, но не быстрее `MAX_SPEED`.
+    pub fn update_speed(&mut self) {
+        let units = self.length() - 1;
+        let speed = self.init_speed + SPEED_PER_UNIT * units as f32;
+        self.head.speed = speed.min(MAX_SPEED);
+    }
+
+    /// Проверка на поражение: почему змейка проиграла, если проиграла.
+    /// Препятствия, бомбы и другие змейки проверяются отдельно, здесь только стены и сама змейка.
+    pub fn death_cause(&self, config: &GameConfig) -> Option<DeathCause> {
+        // Либо при пересечении с сегментом, если змейка не неуязвима.
+        if !self.invincible && self.intersect_self() {
+            return Some(DeathCause::Self_);
+        }
+
+        // Либо при пересечении с границами поля, если сквозь них нельзя пройти.
+        let max_coord = config.half_extents() - self.radius;
+        let head = self.head.position().abs();
+        let intersect_wall = !config.walls_wrap() && (head.x > max_coord.x || head.y > max_coord.y);
+        intersect_wall.then_some(DeathCause::Wall)
+    }
+
+    /// Сколько ещё остаётся голове до столкновения со стеной или с собственным сегментом.
+    /// Сквозные стены и, у неуязвимой змейки, собственное тело опасности не представляют.
+    pub fn danger_distance(&self, config: &GameConfig) -> f32 {
+        let head = self.head.position();
+        let wall = if config.walls_wrap() {
+            f32::INFINITY
+        } else {
+            (config.half_extents() - self.radius - head.abs()).min_element()
+        };
+        let body = if self.invincible {
+            f32::INFINITY
+        } else {
+            (DANGER_IGNORED_UNITS.max(self.self_collision_grace)..self.units.len())
+                .filter(|&index| !self.is_stacked(index))
+                .map(|index| {
+                    head.distance(self.units[index].position) - self.radius * SELF_HIT_SCALE
+                })
+                .fold(f32::INFINITY, f32::min)
+        };
+        wall.min(body).max(0.0)
+    }
+
+    /// Пересекается ли голова с собственным сегментом.
+    fn intersect_self(&self) -> bool {
+        self.self_collision_candidates().any(|index| {
+            self.head
+                .intersect(self.units[index].position, self.radius * SELF_HIT_SCALE)
+        })
+    }
+
+    /// Индексы сегментов, с которыми сейчас проверяется столкновение головы.
+    /// Проверяем только сегменты из соседних с головой ячеек сетки.
+    pub fn self_collision_candidates(&self) -> impl Iterator<Item = usize> + '_ {
+        self.grid
+            .neighbours(self.head.position())
+            .filter(|&index| index >= self.self_collision_grace) // пропускаем сегменты, соединённые с головой.
+            .filter(|&index| !self.is_stacked(index))
+    }
+
+    /// Сегмент ещё лежит на предыдущем и не успел разойтись с ним.
+    /// Так бывает у только что добавленных сегментов и у стартовых, сложенных в голове.
+    fn is_stacked(&self, index: usize) -> bool {
+        if index == 0 {
+            return false;
+        }
+        let prev = self.units[index - 1].position;
+        self.units[index].position.distance(prev) < self.radius
+    }
+}
+
+/// По умолчанию у змейки есть только голова, а константы для неё берутся по умолчанию.
+impl Default for Snake {
+    fn default() -> Self {
+        Self::with_length(0, &Config::default())
+    }
+}
+
+impl Snake {
+    /// Змейка с заданным числом сегментов, сложенных в одну точку с головой.
+    /// Сегменты расходятся за головой по мере её движения.
+    pub fn with_length(units_count: usize, tuning: &Config) -> Self {
+        let mut snake = Self {
+            head: Head::new(tuning.init_speed),
+            units: Vec::with_capacity(units_count),
+            grid: SpatialGrid::new(tuning.field_size, tuning.unit_radius),
+            color: WHITE,
+            trail: VecDeque::with_capacity(TRAIL_LENGTH),
+            radius: tuning.unit_radius,
+            init_speed: tuning.init_speed,
+            self_collision_grace: tuning.self_collision_grace,
+            portal: None,
+            portal_cooldown: 0.0,
+            invincible: false,
+            pending_growth: 0,
+            tail_growth: 0.0,
+            cell_progress: 0.0,
+            turn_held: 0.0,
+            queued_turn: 0.0,
+        };
+        snake.reset(units_count, tuning);
+        snake
+    }
+
+    /// Возвращаем змейку в начальное состояние, как у новой змейки из `with_length`:
+    /// голова в центре поля смотрит вправо, а заданное число сегментов сложено в ней.
+    /// След, анимации, порталы и ещё не выросшие сегменты сбрасываются, константы
+    /// берутся из `tuning`. Цвет остаётся прежним, а память под сегменты и след
+    /// не освобождается.
+    pub fn reset(&mut self, units_count: usize, tuning: &Config) {
+        self.radius = tuning.unit_radius;
+        self.init_speed = tuning.init_speed;
+        // Голова всегда касается первого сегмента, поэтому хотя бы его пропускаем,
+        // что бы ни было написано в `config.toml`.
+        self.self_collision_grace = tuning.self_collision_grace.max(1);
+        if self.grid.cell_size != 2.0 * self.radius {
+            self.grid = SpatialGrid::new(tuning.field_size, self.radius);
+        }
+        self.head = Head::new(self.init_speed);
+        self.units.clear();
+        self.units.resize(units_count, self.head.unit);
+        self.trail.clear();
+        self.portal = None;
+        self.portal_cooldown = 0.0;
+        self.invincible = false;
+        self.pending_growth = 0;
+        self.tail_growth = 0.0;
+        self.cell_progress = 0.0;
+        self.turn_held = 0.0;
+        self.queued_turn = 0.0;
+        self.grid.rebuild(&self.units, tuning.field_size);
+        self.update_speed();
+    }
+
+    /// Переносим змейку целиком в заданную точку.
+    pub fn move_to(&mut self, position: Vec2) {
+        self.head.unit.position = position;
+        for unit in &mut self.units {
+            unit.position = position;
+        }
+        let field_size = self.grid.field_size;
+        self.grid.rebuild(&self.units, field_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::FIELD_SIZE;
+    use crate::fruit::FRUIT_RADIUS;
+    use crate::random::{random_position, RNG_LOCK};
+
+    /// Пересечение с сегментом простым перебором, как было до появления сетки.
+    fn intersect_self_naive(snake: &Snake) -> bool {
+        (1..snake.units.len())
+            .filter(|&index| !snake.is_stacked(index))
+            .any(|index| {
+                snake
+                    .head
+                    .intersect(snake.units[index].position, UNIT_RADIUS * SELF_HIT_SCALE)
+            })
+    }
+
+    #[test]
+    fn head_pops_after_eating_and_opens_mouth_near_fruit() {
+        let mut head = Snake::default().head;
+        head.bite();
+        head.animate(EAT_ANIM_DURATION / 2.0, None);
+        assert!((head.eat_anim - 0.5).abs() < 1e-6);
+        assert_eq!(head.mouth_open, 0.0);
+
+        head.animate(EAT_ANIM_DURATION, Some(MOUTH_OPEN_DISTANCE / 4.0));
+        assert_eq!(head.eat_anim, 0.0);
+        assert!((head.mouth_open - 0.75).abs() < 1e-6);
+        head.animate(0.01, Some(2.0 * MOUTH_OPEN_DISTANCE));
+        assert_eq!(head.mouth_open, 0.0);
+    }
+
+    #[test]
+    fn turning_ramps_up_and_decays() {
+        let mut head = Snake::default().head;
+        head.steer(ROTATION_PER_SEC, ROTATION_PER_SEC, 0.01);
+        assert!(head.angular_velocity > 0.0 && head.angular_velocity < ROTATION_PER_SEC);
+
+        for _ in 0..100 {
+            head.steer(ROTATION_PER_SEC, ROTATION_PER_SEC, 0.01);
+        }
+        assert_eq!(head.angular_velocity, ROTATION_PER_SEC);
+
+        // Желаемый поворот быстрее наибольшего всё равно упирается в наибольший.
+        head.steer(10.0 * ROTATION_PER_SEC, ROTATION_PER_SEC, 0.01);
+        assert_eq!(head.angular_velocity, ROTATION_PER_SEC);
+
+        head.steer(0.0, ROTATION_PER_SEC, 0.01);
+        assert!(head.angular_velocity > 0.0 && head.angular_velocity < ROTATION_PER_SEC);
+        for _ in 0..100 {
+            head.steer(0.0, ROTATION_PER_SEC, 0.01);
+        }
+        assert_eq!(head.angular_velocity, 0.0);
+    }
+
+    #[test]
+    fn unit_intersects_only_overlapping_circles() {
+        let unit = Unit {
+            position: Vec2::ZERO,
+        };
+        let radius = 0.06;
+        let touching = radius + UNIT_RADIUS;
+        assert!(unit.intersect(Vec2::new(touching - 1e-4, 0.0), touching));
+        // Касание ещё не пересечение.
+        assert!(!unit.intersect(Vec2::new(touching, 0.0), touching));
+        assert!(!unit.intersect(Vec2::new(0.0, -touching - 1e-4), touching));
+    }
+
+    #[test]
+    fn unit_keeps_two_radii_behind_previous() {
+        let mut unit = Unit {
+            position: Vec2::ZERO,
+        };
+        let prev = Vec2::new(0.3, 0.4);
+        unit.go(prev, UNIT_RADIUS);
+        assert!((unit.position.distance(prev) - 2.0 * UNIT_RADIUS).abs() < 1e-6);
+        // Сдвигаемся точно в сторону предыдущего элемента.
+        assert!(unit.position.perp_dot(prev).abs() < 1e-6);
+
+        // Если предыдущий элемент и так близко, то стоим на месте.
+        let mut close = Unit {
+            position: Vec2::ZERO,
+        };
+        close.go(Vec2::new(UNIT_RADIUS, 0.0), UNIT_RADIUS);
+        assert_eq!(close.position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn low_grip_drifts_on_turns_but_reaches_full_speed() {
+        let mut head = Snake::default().head;
+        head.velocity = Vec2::ZERO;
+        for _ in 0..200 {
+            head.go(0.01, 5.0);
+        }
+        assert!((head.velocity - head.speed * Vec2::X).length() < 1e-3);
+
+        // Резко повернули: голова ещё какое-то время едет в прежнюю сторону.
+        head.direction = Vec2::Y;
+        head.go(0.01, 5.0);
+        assert!(head.velocity.x > 0.9 * head.speed);
+        assert!(head.velocity.y > 0.0);
+
+        // С обычным сцеплением голова сразу едет туда, куда смотрит.
+        head.go(0.01, HEAD_GRIP);
+        assert_eq!(head.velocity, head.speed * Vec2::Y);
+    }
+
+    #[test]
+    fn added_units_settle_two_radii_apart() {
+        let config = GameConfig::default();
+        let mut snake = Snake::default();
+        for _ in 0..100 {
+            snake.go(0.01, 0.0, &config);
+        }
+        for _ in 0..5 {
+            snake.add_unit();
+        }
+        // Хватает, чтобы все сегменты выросли и разошлись по местам.
+        for _ in 0..300 {
+            snake.go(0.01, 0.0, &config);
+        }
+
+        assert_eq!(snake.units.len(), 5);
+        let positions: Vec<_> = snake.segments().collect();
+        for pair in positions.windows(2) {
+            assert!((pair[0].distance(pair[1]) - 2.0 * snake.radius).abs() < 1e-5);
+            // Змейка ехала прямо, поэтому и хвост лёг на ту же прямую.
+            assert!(pair[1].y.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn reset_snake_equals_fresh_one() {
+        let config = GameConfig::default();
+        let mut snake = Snake::with_length(4, &config.tuning);
+        for _ in 0..50 {
+            snake.go(0.02, 1.0, &config);
+        }
+        snake.add_unit();
+        snake.head.eat_anim = 1.0;
+        snake.invincible = true;
+        assert_ne!(snake, Snake::default());
+
+        let capacity = snake.units.capacity();
+        snake.reset(2, &config.tuning);
+        assert_eq!(snake, Snake::with_length(2, &config.tuning));
+        assert_eq!(snake.units.capacity(), capacity);
+    }
+
+    #[test]
+    fn segments_go_from_head_to_tail() {
+        let mut snake = Snake::with_length(3, &Config::default());
+        snake.head.unit.position = Vec2::new(0.5, 0.0);
+        let segments: Vec<_> = snake.segments().collect();
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[0], snake.head.position());
+        assert_eq!(segments[3], snake.units[2].position);
+    }
+
+    #[test]
+    fn snake_eats_only_touching_fruit() {
+        let snake = Snake::default();
+        let fruit = |x| Fruit::at(Vec2::new(x, 0.0), FRUIT_RADIUS);
+        let reach = UNIT_RADIUS + FRUIT_RADIUS;
+        assert!(snake.can_eat(&fruit(reach - 0.01)));
+        assert!(!snake.can_eat(&fruit(reach + 0.01)));
+    }
+
+    #[test]
+    fn snake_loses_on_wall() {
+        let config = GameConfig::default();
+        let limit = config.half_size() - UNIT_RADIUS;
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(0.0, limit - 0.01);
+        assert!(snake.death_cause(&config).is_none());
+        snake.head.unit.position = Vec2::new(0.0, -limit - 0.01);
+        assert!(snake.death_cause(&config).is_some());
+        snake.head.unit.position = Vec2::new(limit + 0.01, 0.0);
+        assert_eq!(snake.death_cause(&config), Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn snake_loses_on_own_body() {
+        let config = GameConfig::default();
+        let mut snake = Snake::default();
+        let body = |points: &[(f32, f32)]| {
+            points
+                .iter()
+                .map(|&(x, y)| Unit {
+                    position: Vec2::new(x, y),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Змейка свернулась, и голова легла на третий сегмент.
+        snake.units = body(&[(0.08, 0.0), (0.08, 0.08), (0.0, 0.02)]);
+        snake.grid.rebuild(&snake.units, config.field_size);
+        assert_eq!(snake.death_cause(&config), Some(DeathCause::Self_));
+
+        // Первый сегмент всегда касается головы, это не столкновение.
+        snake.units = body(&[(0.02, 0.0), (0.1, 0.0), (0.18, 0.0)]);
+        snake.grid.rebuild(&snake.units, config.field_size);
+        assert_eq!(snake.death_cause(&config), None);
+    }
+
+    #[test]
+    fn grace_forgives_tight_turn_into_second_unit() {
+        let config = GameConfig::default();
+        let units = [(0.08, 0.0), (0.0, 0.03), (-0.08, 0.03)]
+            .iter()
+            .map(|&(x, y)| Unit {
+                position: Vec2::new(x, y),
+            })
+            .collect();
+        let mut snake = Snake {
+            units,
+            ..Snake::default()
+        };
+        snake.grid.rebuild(&snake.units, config.field_size);
+        assert!(snake.death_cause(&config).is_some());
+
+        snake.self_collision_grace = 3;
+        assert!(snake.death_cause(&config).is_none());
+    }
+
+    #[test]
+    fn zero_collision_grace_does_not_panic() {
+        let config = GameConfig {
+            tuning: Config {
+                self_collision_grace: 0,
+                ..Config::default()
+            },
+            ..GameConfig::default()
+        };
+        let mut snake = Snake::with_length(5, &config.tuning);
+        assert_eq!(snake.self_collision_grace, 1);
+        snake.go(0.05, 0.0, &config);
+        assert!(snake.death_cause(&config).is_none());
+        assert!(snake.self_collision_candidates().all(|index| index > 0));
+    }
+
+    #[test]
+    fn danger_distance_sees_walls_and_body() {
+        let config = GameConfig::default();
+        let mut snake = Snake::default();
+        let limit = config.half_size() - UNIT_RADIUS;
+        snake.head.unit.position = Vec2::new(limit - 0.02, 0.0);
+        assert!((snake.danger_distance(&config) - 0.02).abs() < 1e-5);
+
+        // Сквозь стены можно пройти, поэтому у них не опасно.
+        let wrapping = GameConfig {
+            wrap_walls: true,
+            ..GameConfig::default()
+        };
+        assert_eq!(snake.danger_distance(&wrapping), f32::INFINITY);
+
+        // Хвост, к которому голова подошла почти вплотную, опасен, а ближние сегменты нет.
+        snake.head.unit.position = Vec2::ZERO;
+        snake.units = (1..=DANGER_IGNORED_UNITS + 1)
+            .map(|i| Unit {
+                position: Vec2::new(0.0, i as f32 * 0.05),
+            })
+            .collect();
+        assert!(snake.danger_distance(&config) > 0.1);
+        snake.units.last_mut().unwrap().position = Vec2::new(UNIT_RADIUS * 2.0, 0.0);
+        assert!((snake.danger_distance(&config) - UNIT_RADIUS * 0.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn huge_step_does_not_skip_fruit_or_wall() {
+        let config = GameConfig::default();
+        let fruit = Fruit::at(Vec2::new(0.5, 0.0), FRUIT_RADIUS);
+
+        // За такой шаг голова ушла бы далеко за фрукт.
+        let mut snake = Snake::default();
+        let dt = 2.0;
+        assert!(snake.head.speed * dt > fruit.position.x + 2.0 * FRUIT_RADIUS);
+        let events = snake.update(dt, 0.0, std::slice::from_ref(&fruit), &config);
+        assert_eq!(events, vec![Event::AteFruit { index: 0 }]);
+
+        // О стену змейка разбивается у самой стены, а не где-то за ней.
+        let events = snake.update(100.0, 0.0, &[], &config);
+        assert_eq!(events, vec![Event::Died(DeathCause::Wall)]);
+        assert!(snake.head.position().x < config.half_size() + UNIT_RADIUS);
+    }
+
+    #[test]
+    fn eating_does_not_cut_the_step_short() {
+        let config = GameConfig::default();
+        let fruit = Fruit::at(Vec2::new(0.1, 0.0), FRUIT_RADIUS);
+        let dt = 0.5;
+        let mut hungry = Snake::default();
+        let mut fed = Snake::default();
+        assert_eq!(hungry.update(dt, 0.0, &[], &config), vec![]);
+        let events = fed.update(dt, 0.0, std::slice::from_ref(&fruit), &config);
+        assert_eq!(events, vec![Event::AteFruit { index: 0 }]);
+        assert_eq!(fed.head.position(), hungry.head.position());
+        assert!(fed.head.position().x > fruit.position.x + FRUIT_RADIUS);
+    }
+
+    #[test]
+    fn invincible_snake_passes_through_itself_but_not_walls() {
+        let config = GameConfig::default();
+        let units = [(0.08, 0.0), (0.08, 0.08), (0.0, 0.02)]
+            .iter()
+            .map(|&(x, y)| Unit {
+                position: Vec2::new(x, y),
+            })
+            .collect();
+        let mut snake = Snake {
+            units,
+            ..Snake::default()
+        };
+        snake.grid.rebuild(&snake.units, config.field_size);
+        assert!(snake.death_cause(&config).is_some());
+
+        snake.invincible = true;
+        assert!(snake.death_cause(&config).is_none());
+
+        // О стену неуязвимая змейка всё так же разбивается.
+        snake.head.unit.position = Vec2::new(config.half_size(), 0.02);
+        assert_eq!(
+            snake.update(0.01, 0.0, &[], &config),
+            vec![Event::Died(DeathCause::Wall)]
+        );
+    }
+
+    #[test]
+    fn update_reports_events() {
+        let config = GameConfig::default();
+        let fruit = |x| Fruit::at(Vec2::new(x, 0.0), FRUIT_RADIUS);
+        let fruits = [fruit(-0.5), fruit(0.1)];
+
+        let mut snake = Snake::default();
+        assert_eq!(snake.update(0.01, 0.0, &fruits[..1], &config), vec![]);
+        assert_eq!(
+            snake.update(0.01, 0.0, &fruits, &config),
+            vec![Event::AteFruit { index: 1 }]
+        );
+
+        snake.head.unit.position = Vec2::new(config.half_size(), 0.0);
+        assert_eq!(
+            snake.update(0.01, 0.0, &fruits[..1], &config),
+            vec![Event::Died(DeathCause::Wall)]
+        );
+
+        // Голова, повернув, врезается в третий сегмент.
+        let units = [(0.08, 0.0), (0.08, 0.08), (0.0, 0.02)]
+            .iter()
+            .map(|&(x, y)| Unit {
+                position: Vec2::new(x, y),
+            })
+            .collect();
+        let mut snake = Snake {
+            units,
+            ..Snake::default()
+        };
+        assert_eq!(
+            snake.update(0.01, 0.0, &[], &config),
+            vec![Event::Died(DeathCause::Self_)]
+        );
+    }
+
+    #[test]
+    fn grid_matches_naive_self_intersection() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        rand::srand(42);
+        let config = GameConfig::default();
+        for _ in 0..500 {
+            let mut snake = Snake::default();
+            snake.head.unit.position = random_position(&config) * 0.9;
+            let units_count = rand::gen_range(0, 60);
+            snake.units = (0..units_count)
+                .map(|_| Unit {
+                    position: random_position(&config) * 0.9,
+                })
+                .collect();
+            snake.grid.rebuild(&snake.units, config.field_size);
+
+            assert_eq!(snake.intersect_self(), intersect_self_naive(&snake));
+        }
+    }
+
+    #[test]
+    fn grid_finds_units_outside_field() {
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(FIELD_SIZE, 0.0);
+        snake.units = vec![
+            Unit {
+                position: Vec2::ZERO,
+            },
+            Unit {
+                position: Vec2::new(FIELD_SIZE + UNIT_RADIUS, 0.0),
+            },
+        ];
+        snake.grid.rebuild(&snake.units, FIELD_SIZE);
+
+        assert!(snake.intersect_self());
+    }
+
+    #[test]
+    fn wrapped_units_follow_across_edge() {
+        let config = GameConfig {
+            wrap_walls: true,
+            ..GameConfig::default()
+        };
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(config.half_size() - 0.1, 0.0);
+        for _ in 0..5 {
+            snake.add_unit();
+        }
+        for _ in 0..100 {
+            snake.go(0.01, 0.0, &config);
+        }
+
+        // Голова перешла на другую сторону поля, а сегменты не растянулись через всё поле.
+        assert!(snake.head.position().x < 0.0);
+        let mut prev = snake.head.position();
+        for unit in &snake.units {
+            let gap = wrap_position(prev - unit.position, config.field_extents()).length();
+            assert!(gap <= 2.0 * UNIT_RADIUS + 1e-4);
+            prev = unit.position;
+        }
+        assert!(snake.death_cause(&config).is_none());
+    }
+
+    #[test]
+    fn speed_grows_with_length_up_to_max() {
+        let mut snake = Snake::default();
+        assert_eq!(snake.head.speed, INIT_SPEED);
+
+        snake.add_unit();
+        assert!(snake.head.speed > INIT_SPEED);
+
+        for _ in 0..1000 {
+            snake.add_unit();
+        }
+        assert_eq!(snake.head.speed, MAX_SPEED);
+    }
+
+    #[test]
+    fn body_fades_from_head_to_tail() {
+        let head = Color::new(1.0, 0.5, 0.0, 0.8);
+        let first = body_color(head, 0, 4);
+        assert_eq!(Skin::Striped.segment_color(first, 0), first);
+        assert_ne!(Skin::Striped.segment_color(first, 1), first);
+        assert_eq!(Skin::Spotted.segment_color(first, 1), first);
+        let last = body_color(head, 3, 4);
+        assert!(first.r < head.r && first.r > last.r);
+        assert!((last.r - TAIL_BRIGHTNESS).abs() < 1e-6);
+        assert_eq!(last.a, head.a);
+
+        // Единственный сегмент сразу получает цвет хвоста, а пустое тело ничего не ломает.
+        assert_eq!(body_color(head, 0, 1), last);
+        assert!(body_color(head, 0, 0).r.is_finite());
+    }
+
+    #[test]
+    fn growth_spreads_tail_smoothly() {
+        let config = GameConfig::default();
+        let mut snake = Snake::with_length(3, &config.tuning);
+        for _ in 0..300 {
+            snake.go(0.01, 0.0, &config);
+        }
+        for _ in 0..3 {
+            snake.add_unit();
+        }
+        assert_eq!(snake.length(), 7);
+
+        // Пока хвост растёт, сегменты не прыгают: за шаг никто не сдвигается дальше головы.
+        let dt = 0.01;
+        let max_step = snake.head.speed * dt + 1e-5;
+        for _ in 0..200 {
+            let before: Vec<_> = snake.segments().collect();
+            snake.go(dt, 0.0, &config);
+            for (prev, now) in before.iter().zip(snake.segments()) {
+                assert!(prev.distance(now) <= max_step);
+            }
+        }
+
+        // Все съеденные сегменты выросли и разошлись на обычное расстояние.
+        assert_eq!(snake.pending_growth, 0);
+        assert_eq!(snake.units.len(), 6);
+        let positions: Vec<_> = snake.segments().collect();
+        for pair in positions.windows(2) {
+            assert!((pair[0].distance(pair[1]) - 2.0 * UNIT_RADIUS).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn removing_units_stops_at_head() {
+        let mut snake = Snake::default();
+        snake.add_unit();
+        snake.add_unit();
+        let speed = snake.head.speed;
+
+        snake.remove_unit();
+        assert_eq!(snake.length(), 2);
+        assert!(snake.head.speed < speed);
+
+        snake.remove_unit();
+        snake.remove_unit();
+        assert_eq!(snake.length(), 1);
+        assert_eq!(snake.head.speed, INIT_SPEED);
+    }
+
+    #[test]
+    fn starting_units_fan_out_behind_head() {
+        let config = GameConfig::default();
+        let mut snake = Snake::with_length(5, &config.tuning);
+        assert_eq!(snake.length(), 6);
+        assert!(snake.units.iter().all(|unit| unit.position == Vec2::ZERO));
+
+        // Пока сегменты расходятся, змейка не считается врезавшейся в себя.
+        for _ in 0..100 {
+            snake.go(0.01, 0.0, &config);
+            assert!(snake.death_cause(&config).is_none());
+        }
+
+        let mut prev = snake.head.position();
+        for unit in &snake.units {
+            let gap = prev.distance(unit.position);
+            assert!((gap - 2.0 * UNIT_RADIUS).abs() < 1e-4);
+            prev = unit.position;
+        }
+        assert_eq!(Snake::default().length(), 1);
+    }
+
+    #[test]
+    fn trail_is_bounded() {
+        let config = GameConfig::default();
+        let mut snake = Snake::default();
+        for _ in 0..TRAIL_LENGTH * 3 {
+            snake.go(0.01, 0.0, &config);
+        }
+        assert_eq!(snake.trail.len(), TRAIL_LENGTH);
+        assert!(Snake::default().trail.is_empty());
+    }
+
+    /// Змейка заданной длины, уложенная змейкой же: ряд за рядом, сегмент к сегменту.
+    /// Голова в начале верхнего ряда движется вправо и ни во что не упирается.
+    fn coiled_snake(length: usize, config: &GameConfig) -> Snake {
+        let step = 2.0 * UNIT_RADIUS;
+        let columns = 100;
+        let mut snake = Snake::default();
+        snake.head.unit.position = Vec2::new(0.0, 4.0);
+        snake.units = (0..length)
+            .map(|index| {
+                let (row, column) = (index / columns, index % columns);
+                let x = if row % 2 == 0 {
+                    -step * (column + 1) as f32
+                } else {
+                    step * (column as f32 - columns as f32)
+                };
+                Unit {
+                    position: Vec2::new(x, 4.0 - step * row as f32),
+                }
+            })
+            .collect();
+        snake.grid.rebuild(&snake.units, config.field_size);
+        snake
+    }
+
+    /// Сколько стоит один шаг очень длинной змейки: `go` и проверка `death_cause`.
+    /// Окно не нужно. Запускается отдельно, в сборке с оптимизациями:
+    /// `cargo test --release bench_go -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_go_and_death_cause() {
+        const STEPS: u32 = 100;
+        let config = GameConfig {
+            field_size: 12.0,
+            ..GameConfig::default()
+        };
+        for length in [100, 1000, 10_000] {
+            let mut snake = coiled_snake(length, &config);
+            let start = std::time::Instant::now();
+            for _ in 0..STEPS {
+                snake.go(1.0 / 60.0, 0.0, &config);
+                assert!(std::hint::black_box(snake.death_cause(&config)).is_none());
+            }
+            let per_step = start.elapsed() / STEPS;
+            println!("{length:>6} units: {per_step:?} per go + death_cause");
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Снимок состояния раунда в JSON для отладки и внешних инструментов.
+//! В отличие от сохранения, снимок только читают: из него раунд не восстанавливается.
+
+use macroquad::prelude::*;
+use serde::Serialize;
+
+use crate::save;
+use crate::simulation::Game;
+
+/// Что видно о змейке игрока.
+#[derive(Serialize)]
+pub struct SnakeSnapshot {
+    pub score: u32,
+    #[serde(with = "save::vec2")]
+    pub head: Vec2,
+    #[serde(with = "save::vec2")]
+    pub direction: Vec2,
+    /// Скорость головы с учётом бонусов и ускорения.
+    pub speed: f32,
+    /// Позиции сегментов от головы к хвосту, без самой головы.
+    pub units: Vec<[f32; 2]>,
+}
+
+/// Снимок раунда.
+#[derive(Serialize)]
+pub struct Snapshot {
+    pub round_time: f32,
+    /// Лучший счёт среди игроков.
+    pub score: u32,
+    pub snakes: Vec<SnakeSnapshot>,
+    pub fruits: Vec<[f32; 2]>,
+}
+
+impl Snapshot {
+    /// Снимаем раунд, ничего в нём не меняя.
+    pub fn new(game: &Game) -> Self {
+        let snakes = game
+            .players
+            .iter()
+            .map(|player| {
+                let head = &player.snake.head;
+                SnakeSnapshot {
+                    score: player.score,
+                    head: head.position(),
+                    direction: head.direction,
+                    speed: head.speed * head.speed_multiplier,
+                    units: player
+                        .snake
+                        .segments()
+                        .skip(1)
+                        .map(|position| position.to_array())
+                        .collect(),
+                }
+            })
+            .collect();
+        Self {
+            round_time: game.config.round_time,
+            score: game.score(),
+            snakes,
+            fruits: game.fruits.iter().map(|f| f.position.to_array()).collect(),
+        }
+    }
+
+    /// Снимок одной строкой JSON, чтобы его было удобно разбирать построчно.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("snapshot is always serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::GameConfig;
+    use crate::player::PlayersMode;
+    use crate::random::RNG_LOCK;
+
+    #[test]
+    fn snapshot_is_one_json_line_with_snakes_and_fruits() {
+        let _rng = RNG_LOCK.lock().unwrap();
+        let mut game = Game::new(GameConfig::default(), PlayersMode::VersusAi);
+        game.players[1].score = 7;
+        game.players[0].snake.add_unit();
+        game.players[0].snake.go(0.1, 0.0, &game.config);
+
+        let json = Snapshot::new(&game).to_json();
+        assert!(!json.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["score"], 7);
+        assert_eq!(value["snakes"].as_array().unwrap().len(), 2);
+        let first = &value["snakes"][0];
+        assert_eq!(first["units"].as_array().unwrap().len(), 1);
+        let head = game.players[0].snake.head.position();
+        assert_eq!(first["head"][0].as_f64().unwrap() as f32, head.x);
+        assert_eq!(first["direction"][0], 1.0);
+        assert_eq!(value["fruits"].as_array().unwrap().len(), game.fruits.len());
+    }
+}
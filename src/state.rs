@@ -0,0 +1,291 @@
+//! Состояния игры и переходы между ними.
+//! Игровой цикл решает, какое событие произошло, а куда оно ведёт, решает `transition`.
+
+use macroquad::prelude::*;
+
+/// Сколько секунд длится затемнение при смене экрана: половину экран гаснет, половину светлеет.
+pub const FADE_DURATION: f32 = 0.4;
+
+/// Состояние игры.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameState {
+    /// Главное меню, с которого начинается игра.
+    Menu,
+    /// Экран настроек звука и сложности. Храним выбранную строку.
+    Settings { selected: usize },
+    /// Обратный отсчёт перед началом раунда. Храним, сколько секунд он уже идёт.
+    Countdown { elapsed: f32 },
+    /// Идёт игра.
+    Playing,
+    /// Игра приостановлена. Храним выбранный пункт меню паузы.
+    Paused { selected: usize },
+    /// Результат попал в таблицу рекордов, и игрок вводит своё имя.
+    NameEntry { score: u32 },
+    /// Змейка проиграла. Храним набранные очки, чтобы показать их на экране.
+    GameOver { score: u32 },
+    /// Змейка доросла до цели в игре на длину. Храним, за сколько секунд.
+    Won { time: f32 },
+    /// Показываем запись раунда. Храним номер следующего кадра записи.
+    Replay { frame: usize },
+}
+
+/// Событие, от которого может смениться состояние игры.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StateInput {
+    /// Начинаем раунд с обратного отсчёта: из меню или заново после конца раунда.
+    Start,
+    /// Открываем настройки из меню.
+    OpenSettings,
+    /// Прошло `dt` секунд обратного отсчёта, который длится `duration` секунд.
+    CountdownTick { dt: f32, duration: f32 },
+    /// Ставим игру на паузу или снимаем с паузы.
+    Pause,
+    /// Раунд закончился. Победа хранит время раунда, а `qualifies` говорит,
+    /// попал ли результат в таблицу рекордов.
+    RoundEnded {
+        score: u32,
+        won: Option<f32>,
+        qualifies: bool,
+    },
+    /// Игрок ввёл имя для таблицы рекордов.
+    NameEntered,
+    /// Начинаем показывать запись раунда.
+    WatchReplay,
+    /// Возвращаемся в главное меню.
+    ToMenu,
+}
+
+/// Состояние, в которое игра переходит из `state` после события `input`.
+/// Событие, которое в этом состоянии ничего не значит, состояние не меняет.
+pub fn transition(state: GameState, input: StateInput) -> GameState {
+    use GameState::*;
+    match (state, input) {
+        (Menu | GameOver { .. } | Won { .. }, StateInput::Start) => Countdown { elapsed: 0.0 },
+        (Menu, StateInput::OpenSettings) => Settings { selected: 0 },
+        (Countdown { elapsed }, StateInput::CountdownTick { dt, duration }) => {
+            let elapsed = elapsed + dt;
+            if elapsed >= duration {
+                Playing
+            } else {
+                Countdown { elapsed }
+            }
+        }
+        (Playing, StateInput::Pause) => Paused { selected: 0 },
+        (Paused { .. }, StateInput::Pause) => Playing,
+        (
+            Playing | Replay { .. },
+            StateInput::RoundEnded {
+                score,
+                won,
+                qualifies,
+            },
+        ) => match won {
+            Some(time) => Won { time },
+            // Рекорд при просмотре записи не обновляем.
+            None if qualifies && state == Playing => NameEntry { score },
+            None => GameOver { score },
+        },
+        (NameEntry { score }, StateInput::NameEntered) => GameOver { score },
+        (Menu | GameOver { .. } | Won { .. }, StateInput::WatchReplay) => Replay { frame: 0 },
+        (_, StateInput::ToMenu) => Menu,
+        (state, _) => state,
+    }
+}
+
+impl GameState {
+    /// Экран, к которому относится состояние: меню, раунд или его итог.
+    /// Между разными экранами переходим через затемнение.
+    fn screen(self) -> usize {
+        match self {
+            Self::Menu | Self::Settings { .. } => 0,
+            Self::Countdown { .. } | Self::Playing | Self::Paused { .. } | Self::Replay { .. } => 1,
+            Self::NameEntry { .. } | Self::GameOver { .. } | Self::Won { .. } => 2,
+        }
+    }
+}
+
+/// Что сделать с раундом при смене экрана. Раунд меняем не сразу, а в самой тёмной точке
+/// затемнения: до неё старый экран, например итоги раунда, показывает ещё старый раунд.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundAction {
+    /// Начинаем новый раунд со своим зерном и записываем его.
+    NewRound,
+    /// Начинаем раунд из записи, чтобы её показать.
+    WatchReplay,
+}
+
+/// Затемнение при смене экрана. Пока экран гаснет, показываем старое состояние,
+/// а в самой тёмной точке переключаемся на новое. Во время затемнения ввод не принимается.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    pub from: GameState,
+    pub to: GameState,
+    /// Сколько прошло от начала затемнения до его конца: от 0 до 1.
+    pub progress: f32,
+    /// Что сделать с раундом, когда покажется новое состояние. Выполняется один раз.
+    pub action: Option<RoundAction>,
+}
+
+impl Transition {
+    /// Затемнение, если `from` и `to` относятся к разным экранам.
+    pub fn between(from: GameState, to: GameState) -> Option<Self> {
+        (from.screen() != to.screen()).then_some(Self {
+            from,
+            to,
+            progress: 0.0,
+            action: None,
+        })
+    }
+
+    /// То же затемнение, которое в самой тёмной точке ещё и меняет раунд.
+    pub fn with_action(self, action: Option<RoundAction>) -> Self {
+        Self { action, ..self }
+    }
+
+    /// Действие с раундом, если уже пора его выполнить. Второй раз не возвращается.
+    pub fn take_action(&mut self) -> Option<RoundAction> {
+        if self.progress < 0.5 {
+            return None;
+        }
+        self.action.take()
+    }
+
+    /// Продвигаем затемнение. Возвращаем, идёт ли оно ещё.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.progress = (self.progress + dt / FADE_DURATION).min(1.0);
+        self.progress < 1.0
+    }
+
+    /// Какое состояние сейчас показываем.
+    pub fn state(&self) -> GameState {
+        if self.progress < 0.5 {
+            self.from
+        } else {
+            self.to
+        }
+    }
+
+    /// Непрозрачность затемнения: нарастает до середины и спадает к концу.
+    pub fn alpha(&self) -> f32 {
+        1.0 - (2.0 * self.progress - 1.0).abs()
+    }
+
+    /// Затемнение рисуется поверх всего экрана.
+    pub fn draw(&self) {
+        let color = Color::new(0.0, 0.0, 0.0, self.alpha());
+        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ended(score: u32, won: Option<f32>, qualifies: bool) -> StateInput {
+        StateInput::RoundEnded {
+            score,
+            won,
+            qualifies,
+        }
+    }
+
+    #[test]
+    fn round_goes_from_menu_to_game_over_and_back() {
+        let tick = StateInput::CountdownTick {
+            dt: 0.6,
+            duration: 1.0,
+        };
+        let state = transition(GameState::Menu, StateInput::Start);
+        assert_eq!(state, GameState::Countdown { elapsed: 0.0 });
+        let state = transition(state, tick);
+        assert!(matches!(state, GameState::Countdown { .. }));
+        let state = transition(state, tick);
+        assert_eq!(state, GameState::Playing);
+        let state = transition(state, ended(7, None, false));
+        assert_eq!(state, GameState::GameOver { score: 7 });
+        assert_eq!(transition(state, StateInput::ToMenu), GameState::Menu);
+        assert_eq!(
+            transition(state, StateInput::Start),
+            GameState::Countdown { elapsed: 0.0 }
+        );
+    }
+
+    #[test]
+    fn round_end_depends_on_result() {
+        let paused = transition(GameState::Playing, StateInput::Pause);
+        assert_eq!(paused, GameState::Paused { selected: 0 });
+        assert_eq!(transition(paused, StateInput::Pause), GameState::Playing);
+
+        let name_entry = transition(GameState::Playing, ended(50, None, true));
+        assert_eq!(name_entry, GameState::NameEntry { score: 50 });
+        assert_eq!(
+            transition(name_entry, StateInput::NameEntered),
+            GameState::GameOver { score: 50 }
+        );
+        assert_eq!(
+            transition(GameState::Playing, ended(50, Some(12.0), true)),
+            GameState::Won { time: 12.0 }
+        );
+        let replay = GameState::Replay { frame: 3 };
+        assert_eq!(
+            transition(replay, ended(50, None, true)),
+            GameState::GameOver { score: 50 }
+        );
+    }
+
+    #[test]
+    fn fade_swaps_state_at_peak() {
+        let game_over = GameState::GameOver { score: 3 };
+        assert_eq!(
+            Transition::between(GameState::Menu, GameState::Settings { selected: 0 }),
+            None
+        );
+        assert_eq!(
+            Transition::between(GameState::Countdown { elapsed: 0.0 }, GameState::Playing),
+            None
+        );
+
+        let mut fade = Transition::between(GameState::Playing, game_over).unwrap();
+        assert_eq!(fade.alpha(), 0.0);
+        assert!(fade.update(FADE_DURATION * 0.4));
+        assert_eq!(fade.state(), GameState::Playing);
+        assert!(fade.update(FADE_DURATION * 0.1));
+        assert_eq!(fade.state(), game_over);
+        assert!((fade.alpha() - 1.0).abs() < 1e-5);
+        assert!(!fade.update(FADE_DURATION));
+        assert_eq!(fade.alpha(), 0.0);
+    }
+
+    #[test]
+    fn fade_changes_round_only_after_old_screen_is_hidden() {
+        let game_over = GameState::GameOver { score: 3 };
+        let countdown = transition(game_over, StateInput::Start);
+        let mut fade = Transition::between(game_over, countdown)
+            .unwrap()
+            .with_action(Some(RoundAction::NewRound));
+
+        // Пока видны итоги, раунд ещё старый.
+        assert!(fade.update(FADE_DURATION * 0.4));
+        assert_eq!(fade.state(), game_over);
+        assert_eq!(fade.take_action(), None);
+
+        assert!(fade.update(FADE_DURATION * 0.1));
+        assert_eq!(fade.state(), countdown);
+        assert_eq!(fade.take_action(), Some(RoundAction::NewRound));
+        assert_eq!(fade.take_action(), None);
+    }
+
+    #[test]
+    fn meaningless_input_keeps_state() {
+        assert_eq!(
+            transition(GameState::Menu, StateInput::Pause),
+            GameState::Menu
+        );
+        assert_eq!(
+            transition(GameState::Playing, StateInput::Start),
+            GameState::Playing
+        );
+        let replay = GameState::Replay { frame: 3 };
+        assert_eq!(transition(replay, StateInput::Pause), replay);
+    }
+}
@@ -0,0 +1,9 @@
+//! Файлы с данными игры рядом с исполняемым файлом.
+
+use std::path::PathBuf;
+
+/// Путь к файлу с данными игры рядом с исполняемым файлом.
+pub fn data_file_path(file_name: &str) -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(file_name))
+}